@@ -14,7 +14,7 @@ fn main() {
 
     println!("OUT: {}", out_dir);
     println!("PATH: {}", dest_path.display());
-    let mut f = BufWriter::new(File::create(&dest_path).unwrap());
+    let f = BufWriter::new(File::create(&dest_path).unwrap());
 
-    serde_json::to_writer(f, &config);
+    serde_json::to_writer(f, &config).unwrap();
 }
@@ -12,5 +12,6 @@ echo "Monkey patching $DTS_FILE with custom return types"
 
 fix_type "getData" "Data"
 fix_type "getColors" "Colors<string>"
+fix_type "generate" "Generated"
 
 cargo run render - templates/wasm_typescript_types.mustache >> $DTS_FILE
\ No newline at end of file
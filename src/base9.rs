@@ -1,116 +1,354 @@
 
-use ext_palette::rgb::channels::Argb;
 use itertools::Itertools;
-use ext_palette::{
-    Srgb, IntoColor,
-};
-use std::cell::RefCell;
+use ext_palette::{IntoColor, WithAlpha};
+use std::cell::{Cell, RefCell};
 use std::ops::Deref;
 use std::rc::Rc;
-use std::{
-    collections::HashMap,
-};
-use anyhow::{Result, bail};
+use std::collections::HashMap;
+use indexmap::IndexMap;
 use serde_json::{self, Map, Value};
 
-use crate::color_science::{Rgb, self};
-use crate::config::{Config, self};
+use crate::color_science::{ColorSpace, Rgb, self};
+use crate::contrast;
+use crate::config::{Config, VariableOrder, self};
+use crate::expression::{Expression, Function};
+use crate::error::{Error, Result};
+
+/// Color of a variable. Only aliases can be transparent, palette colors are opaque.
+pub type Rgba = ext_palette::Srgba<u8>;
 
 #[derive(Debug)]
 pub(crate) enum ColorMap {
-    Color(Rgb),
-    Map(HashMap<String, Rc<RefCell<ColorMap>>>),
+    Color(Rgba),
+    Map(IndexMap<String, Rc<RefCell<ColorMap>>>),
 }
 
 impl ColorMap {
     fn new_map() -> ColorMap {
-        ColorMap::Map(HashMap::new())
+        ColorMap::Map(IndexMap::new())
     }
 
-    fn insert_color(&mut self, key: String, value: Rgb) -> Result<()> {
-        match self {
-            ColorMap::Color(_) => bail!("can't insert key into color"),
-            ColorMap::Map(map) => {
-                map.insert(key, Rc::new(RefCell::new(ColorMap::Color(value))));
-                Ok(())
-            },
+    /// Only called on maps, inserting into a color is a bug.
+    fn insert_color(&mut self, key: String, value: Rgb) {
+        self.insert(key, Rc::new(RefCell::new(ColorMap::Color(value.into()))));
+    }
+
+    /// Moves the keys of `aliases` to the front, in the same order.
+    fn order_like(&mut self, aliases: &IndexMap<String, config::ColorNames>) {
+        if let ColorMap::Map(map) = self {
+            let mut i = 0;
+            for key in aliases.keys() {
+                if let Some(from) = map.get_index_of(key) {
+                    map.move_index(from, i);
+                    i += 1;
+                }
+            }
         }
     }
-    fn insert(&mut self, key: String, value: Rc<RefCell<ColorMap>>) -> Result<()> {
+
+    /// Only called on maps, inserting into a color is a bug.
+    fn insert(&mut self, key: String, value: Rc<RefCell<ColorMap>>) {
         match self {
-            ColorMap::Color(_) => bail!("can't insert key into color"),
+            ColorMap::Color(_) => unreachable!("can't insert key into color"),
             ColorMap::Map(map) => {
                 map.insert(key, value);
-                Ok(())
             },
         }
     }
 }
 
-fn new_color_shade_map(color: &Rgb, bg: &Rgb, config: &Config) -> Result<Rc<RefCell<ColorMap>>> {
+/// Adds the shades out of gamut to `out_of_gamut`, e.g. `c1.p125`.
+fn new_color_shade_map(name: &str, color: &Rgb, bg: &Rgb, config: &Config, out_of_gamut: &mut Vec<String>) -> Rc<RefCell<ColorMap>> {
     let mut map = ColorMap::new_map();
     for (key, value) in &config.shades {
-        map.insert_color(key.clone(), color_science::mix(bg, color, *value))?;
+        let (shade, mapped) = color_science::mix_in(config.color_space, bg, color, *value);
+        if mapped {
+            out_of_gamut.push(format!("{}.{}", name, key));
+        }
+        map.insert_color(key.clone(), shade);
     }
 
-    Ok(Rc::new(RefCell::new(map)))
+    Rc::new(RefCell::new(map))
 }
 
-fn add_colors(aliases: &HashMap<String, config::ColorNames>, current_map: Rc<RefCell<ColorMap>>, color_map: Rc<RefCell<ColorMap>>) -> Result<()> {
-    for (key, value) in aliases {
-        match value {
-            config::ColorNames::BuiltIn => {},
-            config::ColorNames::Reference(reference) => {
-                let mut ptr: Rc<RefCell<ColorMap>> = color_map.clone();
-                for k in reference.key_iter() {
-                    let tmp = match &*ptr.deref().borrow() {
-                        ColorMap::Color(_) => bail!("..."),
-                        ColorMap::Map(map) => map.get(k).unwrap().clone(),
-                    };
-                    ptr = tmp;
+fn sort_keys(color_map: &Rc<RefCell<ColorMap>>) {
+    if let ColorMap::Map(map) = &mut *color_map.borrow_mut() {
+        map.sort_keys();
+        for value in map.values() {
+            sort_keys(value);
+        }
+    }
+}
+
+/// Resolves the aliases of `colors` in config on demand, so an alias can
+/// reference any other alias regardless of the declaration order.
+struct Resolver<'a> {
+    aliases: &'a IndexMap<String, config::ColorNames>,
+    built_in: Rc<RefCell<ColorMap>>,
+    background: Rgb,
+    foreground: Rgb,
+    color_space: ColorSpace,
+    /// `None` when the alias can't be resolved, the reason is in `errors`.
+    resolved: HashMap<Vec<String>, Option<Rc<RefCell<ColorMap>>>>,
+    /// Aliases being resolved, to detect cycles.
+    visiting: Vec<Vec<String>>,
+    errors: Vec<Error>,
+    /// Colors mapped into gamut, e.g. `c1.p125` or `selection`.
+    out_of_gamut: Vec<String>,
+}
+
+impl<'a> Resolver<'a> {
+    fn new(config: &'a Config) -> Resolver<'a> {
+        let (built_in, out_of_gamut) = get_built_in_variables(config);
+        Resolver {
+            aliases: &config.colors,
+            built_in,
+            background: config.palette.colors[0],
+            foreground: config.palette.colors[1],
+            color_space: config.color_space,
+            resolved: HashMap::new(),
+            visiting: Vec::new(),
+            errors: Vec::new(),
+            out_of_gamut,
+        }
+    }
+
+    /// Resolves every alias and returns the built in colors overridden by them.
+    fn resolve_all(&mut self) -> Rc<RefCell<ColorMap>> {
+        let mut root = ColorMap::new_map();
+        if let ColorMap::Map(map) = &*self.built_in.borrow() {
+            for (key, value) in map {
+                root.insert(key.clone(), value.clone());
+            }
+        }
+        for (key, value) in self.aliases {
+            if let Some(color_map) = self.resolve_alias(std::slice::from_ref(key), value) {
+                root.insert(key.clone(), color_map);
+            }
+        }
+        Rc::new(RefCell::new(root))
+    }
+
+    fn resolve_alias(&mut self, path: &[String], value: &config::ColorNames) -> Option<Rc<RefCell<ColorMap>>> {
+        if let Some(resolved) = self.resolved.get(path) {
+            return resolved.clone();
+        }
+        if let Some(i) = self.visiting.iter().position(|x| x == path) {
+            let mut cycle: Vec<String> = self.visiting[i..].iter().map(|x| x.join(".")).collect();
+            cycle.push(path.join("."));
+            self.errors.push(Error::CyclicReference(cycle));
+            return None;
+        }
+        self.visiting.push(path.to_vec());
+        let resolved = match value {
+            config::ColorNames::BuiltIn => walk(&self.built_in, path).ok(),
+            config::ColorNames::Reference(reference) => self.resolve_reference(reference, path),
+            config::ColorNames::Literal(color) => Some(Rc::new(RefCell::new(ColorMap::Color((*color).into())))),
+            config::ColorNames::Expression(expression) => self.evaluate(expression, path),
+            config::ColorNames::Mapping(aliases) => {
+                let mut map = ColorMap::new_map();
+                for (key, value) in aliases {
+                    let mut child = path.to_vec();
+                    child.push(key.clone());
+                    if let Some(color_map) = self.resolve_alias(&child, value) {
+                        map.insert(key.clone(), color_map);
+                    }
                 }
-                (&mut *current_map.deref().borrow_mut()).insert(key.clone(), ptr.clone())?;
+                Some(Rc::new(RefCell::new(map)))
             },
-            config::ColorNames::Mapping(tmp) => {
-                let map2 = Rc::new(RefCell::new(ColorMap::new_map()));
-                add_colors(tmp, map2.clone(), color_map.clone())?;
-                (&mut *current_map.deref().borrow_mut()).insert(key.clone(), map2)?;
+        };
+        self.visiting.pop();
+        self.resolved.insert(path.to_vec(), resolved.clone());
+        resolved
+    }
+
+    fn resolve_reference(&mut self, reference: &config::Reference, path: &[String]) -> Option<Rc<RefCell<ColorMap>>> {
+        let keys: Vec<String> = reference.key_iter().map(String::from).collect();
+        match self.lookup(&keys) {
+            Ok(resolved) => resolved,
+            Err(Lookup::Unknown) => {
+                self.errors.push(Error::UnknownReference { reference: reference.to_string(), alias: path.join(".") });
+                None
+            },
+            Err(Lookup::IntoColor) => {
+                self.errors.push(Error::ReferenceIntoColor { reference: reference.to_string(), alias: path.join(".") });
+                None
             },
         }
     }
-    Ok(())
-}
 
-pub(crate) fn get_variables(config: &Config) -> Result<Rc<RefCell<ColorMap>>> {
+    fn evaluate(&mut self, expression: &Expression, path: &[String]) -> Option<Rc<RefCell<ColorMap>>> {
+        match expression {
+            Expression::Reference(reference) => self.resolve_reference(reference, path),
+            Expression::Literal(color) => Some(Rc::new(RefCell::new(ColorMap::Color((*color).into())))),
+            Expression::Number(_) => unreachable!("numbers are only function arguments"),
+            Expression::Call(function, args) => {
+                let mut colors = Vec::new();
+                let mut numbers = Vec::new();
+                for arg in args {
+                    match arg {
+                        Expression::Number(number) => numbers.push(*number),
+                        // Evaluates every argument so that all their errors are reported.
+                        _ => colors.push(self.evaluate(arg, path)),
+                    }
+                }
+                let colors: Vec<_> = colors.into_iter().collect::<Option<_>>()?;
+                let (bg, fg, color_space) = (self.background, self.foreground, self.color_space);
+                let out_of_gamut = Cell::new(false);
+                let checked = |(color, mapped): (Rgb, bool)| {
+                    out_of_gamut.set(out_of_gamut.get() || mapped);
+                    color
+                };
+                let result = apply(&colors, &|c: &[Rgba]| match function {
+                    Function::Mix => checked(color_science::mix_in(color_space, &c[0].color, &c[1].color, numbers[0]))
+                        .with_alpha(color_science::mix1d(c[0].alpha as f32, c[1].alpha as f32, numbers[0]).round() as u8),
+                    Function::Lighten => checked(color_science::lighten(&c[0].color, numbers[0])).with_alpha(c[0].alpha),
+                    Function::Darken => checked(color_science::lighten(&c[0].color, -numbers[0])).with_alpha(c[0].alpha),
+                    Function::Alpha => c[0].color.with_alpha((numbers[0].clamp(0., 1.) * 255.).round() as u8),
+                    Function::ContrastOn => {
+                        // What is seen of a transparent color depends on the background.
+                        let c = color_science::blend(&c[0].color, &bg, c[0].alpha as f32 / 255.);
+                        let more_contrast = if contrast::wcag_ratio(&c, &bg) >= contrast::wcag_ratio(&c, &fg) { bg } else { fg };
+                        more_contrast.into()
+                    },
+                });
+                let path = path.join(".");
+                if out_of_gamut.get() && !self.out_of_gamut.contains(&path) {
+                    self.out_of_gamut.push(path);
+                }
+                Some(result)
+            },
+        }
+    }
 
-    let variables_rc = Rc::new(RefCell::new(ColorMap::new_map()));
-    {
-        let variables = &mut *variables_rc.borrow_mut();
+    /// Finds `keys` among the aliases first, then among the built in colors.
+    ///
+    /// Returns `Ok(None)` when `keys` goes through an alias that can't be resolved.
+    fn lookup(&mut self, keys: &[String]) -> Result<Option<Rc<RefCell<ColorMap>>>, Lookup> {
+        let mut aliases = self.aliases;
+        for (i, key) in keys.iter().enumerate() {
+            match aliases.get(key) {
+                Some(config::ColorNames::Mapping(children)) if i + 1 < keys.len() => {
+                    aliases = children;
+                },
+                Some(config::ColorNames::BuiltIn) => return walk(&self.built_in, keys).map(Some),
+                Some(value) => {
+                    return match self.resolve_alias(&keys[..=i], value) {
+                        Some(color_map) => walk(&color_map, &keys[i + 1..]).map(Some),
+                        None => Ok(None),
+                    };
+                },
+                None if i == 0 => return walk(&self.built_in, keys).map(Some),
+                None => return Err(Lookup::Unknown),
+            }
+        }
+        unreachable!("references have at least one key")
+    }
+}
 
-        let bg = config.palette.colors[0];
-        variables.insert_color("background".into(), bg)?;
+enum Lookup {
+    Unknown,
+    IntoColor,
+}
 
-        let fg = config.palette.colors[1];
-        variables.insert("foreground".into(), new_color_shade_map(&fg, &bg, config)?)?;
+fn walk(color_map: &Rc<RefCell<ColorMap>>, keys: &[String]) -> Result<Rc<RefCell<ColorMap>>, Lookup> {
+    let mut ptr: Rc<RefCell<ColorMap>> = color_map.clone();
+    for k in keys {
+        let tmp = match &*ptr.deref().borrow() {
+            ColorMap::Color(_) => return Err(Lookup::IntoColor),
+            ColorMap::Map(map) => map.get(k).cloned().ok_or(Lookup::Unknown)?,
+        };
+        ptr = tmp;
+    }
+    Ok(ptr)
+}
 
-        // c1...c7
-        let hues = &config.palette.colors[2..9];
-        for (i, c) in hues.iter().enumerate() {
-            let name = format!("c{}", i+1);
-            variables.insert(name, new_color_shade_map(c, &bg, config)?)?;
+/// Applies `f` to the colors of `args`, or to the colors under each key when
+/// some of `args` are groups of colors. Keys missing from a group are skipped.
+fn apply(args: &[Rc<RefCell<ColorMap>>], f: &dyn Fn(&[Rgba]) -> Rgba) -> Rc<RefCell<ColorMap>> {
+    let keys: Option<Vec<String>> = args.iter().find_map(|arg| match &*arg.borrow() {
+        ColorMap::Map(map) => Some(map.keys().cloned().collect()),
+        ColorMap::Color(_) => None,
+    });
+    let keys = match keys {
+        Some(keys) => keys,
+        None => {
+            let colors: Vec<Rgba> = args.iter().map(|arg| match &*arg.borrow() {
+                ColorMap::Color(color) => *color,
+                ColorMap::Map(_) => unreachable!(),
+            }).collect();
+            return Rc::new(RefCell::new(ColorMap::Color(f(&colors))));
+        },
+    };
+    let mut result = ColorMap::new_map();
+    for key in keys {
+        let children: Option<Vec<_>> = args.iter().map(|arg| match &*arg.borrow() {
+            ColorMap::Color(_) => Some(arg.clone()),
+            ColorMap::Map(map) => map.get(&key).cloned(),
+        }).collect();
+        if let Some(children) = children {
+            result.insert(key, apply(&children, f));
         }
+    }
+    Rc::new(RefCell::new(result))
+}
 
-        let relative_colors = color_science::get_matching_absolute_color(hues);
+/// Also returns the colors that were out of gamut, see `new_color_shade_map`.
+fn get_built_in_variables(config: &Config) -> (Rc<RefCell<ColorMap>>, Vec<String>) {
+    let mut variables = ColorMap::new_map();
+    let mut out_of_gamut = Vec::new();
 
-        for cnw in relative_colors.into_iter() {
-            variables.insert(cnw.name.into(), new_color_shade_map(&cnw.color, &bg, config)?)?;
-        }
+    let bg = config.palette.colors[0];
+    variables.insert_color("background".into(), bg);
+
+    let fg = config.palette.colors[1];
+    variables.insert("foreground".into(), new_color_shade_map("foreground", &fg, &bg, config, &mut out_of_gamut));
+
+    // c1...c7
+    let hues = &config.palette.colors[2..9];
+    for (i, c) in hues.iter().enumerate() {
+        let name = format!("c{}", i+1);
+        let shades = new_color_shade_map(&name, c, &bg, config, &mut out_of_gamut);
+        variables.insert(name, shades);
+    }
+
+    let relative_colors = color_science::get_matching_absolute_color(hues, &config.absolute);
+
+    for cnw in relative_colors.into_iter() {
+        let shades = new_color_shade_map(&cnw.name, &cnw.color, &bg, config, &mut out_of_gamut);
+        variables.insert(cnw.name, shades);
+    }
+    (Rc::new(RefCell::new(variables)), out_of_gamut)
+}
+
+/// Returns every problem of the aliases in `colors` of config.
+pub(crate) fn validate(config: &Config) -> Vec<Error> {
+    let mut resolver = Resolver::new(config);
+    resolver.resolve_all();
+    resolver.errors
+}
+
+/// Returns the variables that were out of gamut, see `color_science::gamut_map`.
+pub(crate) fn out_of_gamut(config: &Config) -> Vec<String> {
+    let mut resolver = Resolver::new(config);
+    resolver.resolve_all();
+    resolver.out_of_gamut
+}
+
+pub(crate) fn get_variables(config: &Config) -> Result<Rc<RefCell<ColorMap>>> {
+    let mut resolver = Resolver::new(config);
+    let variables_rc = resolver.resolve_all();
+    if let Some(error) = resolver.errors.into_iter().next() {
+        return Err(error);
+    }
+    match config.order {
+        VariableOrder::Declaration => variables_rc.borrow_mut().order_like(&config.colors),
+        VariableOrder::Alphabetical => sort_keys(&variables_rc),
     }
-    add_colors(&config.colors, variables_rc.clone(), variables_rc.clone())?;
     Ok(variables_rc)
 }
 
-fn prefix_to_path(prefix: &Vec<String>) -> Value {
+fn prefix_to_path(prefix: &[String]) -> Value {
     let list: Vec<Value> = prefix.iter().enumerate().map(|(i, name)| {
         let mut map = Map::<String, Value>::new();
         map.insert("name".into(), Value::String(name.into()));
@@ -130,7 +368,7 @@ fn list_color_map(list: &mut Vec<Value>, prefix: &mut Vec<String>, color_map: &R
         ColorMap::Color(c) => {
             let mut mapping: Map<String, Value> = Map::new();
             mapping.insert("path".into(), prefix_to_path(prefix));
-            mapping.insert("color".into(), f(&c));
+            mapping.insert("color".into(), f(c));
             list.push(Value::Object(mapping));
         }
         ColorMap::Map(map) => {
@@ -140,7 +378,7 @@ fn list_color_map(list: &mut Vec<Value>, prefix: &mut Vec<String>, color_map: &R
             list.push(Value::Object(mapping));
             for (key, value) in map {
                 prefix.push(key.clone());
-                list_color_map(list, prefix, &value, f);
+                list_color_map(list, prefix, value, f);
                 prefix.pop();
             }
             let mut mapping: Map<String, Value> = Map::new();
@@ -151,7 +389,7 @@ fn list_color_map(list: &mut Vec<Value>, prefix: &mut Vec<String>, color_map: &R
     }
 }
 
-pub type ColorFn = fn(&Rgb) -> Value;
+pub type ColorFn<'a> = &'a dyn Fn(&Rgba) -> Value;
 
 pub(crate) fn map_color_map(color_map: &Rc<RefCell<ColorMap>>, f: ColorFn ) -> Value {
     match &*color_map.deref().borrow() {
@@ -162,7 +400,7 @@ pub(crate) fn map_color_map(color_map: &Rc<RefCell<ColorMap>>, f: ColorFn ) -> V
             let mut data_map: Map<String, Value> = Map::new();
 
             for (key, value) in map {
-                data_map.insert(key.clone().into(), map_color_map(value, f));
+                data_map.insert(key.clone(), map_color_map(value, f));
             }
 
             Value::Object(data_map)
@@ -174,7 +412,7 @@ pub(crate) fn map_color_map(color_map: &Rc<RefCell<ColorMap>>, f: ColorFn ) -> V
 //     match color_map {
 //         ColorMap::Color(c) => Data::String(f(c)),
 //         ColorMap::Map(map) => {
-//             let mut data_map: HashMap<String, Data> = HashMap::new();
+//             let mut data_map: IndexMap<String, Data> = IndexMap::new();
 //             for (key, value) in map {
 //                 // let tmp: Ref<ColorMap> = value.borrow();
 //                 // data_map.insert(key.clone(), color_map_to_data(value.borrow().borrow(), f));
@@ -184,8 +422,26 @@ pub(crate) fn map_color_map(color_map: &Rc<RefCell<ColorMap>>, f: ColorFn ) -> V
 //     }
 // }
 
-pub fn color_to_format(c: &Rgb) -> Value {
-        let formats: Vec<(&str, fn(&Rgb) -> String)> = vec![
+type FormatFn = fn(&Rgb) -> String;
+type AlphaFormatFn = fn(&Rgba) -> String;
+
+fn to_hsl(c: &Rgb) -> ext_palette::Hsl {
+    c.into_format().into_color()
+}
+
+fn to_hsv(c: &Rgb) -> ext_palette::Hsv {
+    c.into_format().into_color()
+}
+
+/// Formats `x` with at most `digits` decimal places, without trailing zeros.
+fn round(x: f32, digits: i32) -> String {
+    let scale = 10f32.powi(digits);
+    // `+ 0.` turns `-0` into `0`.
+    format!("{}", (x * scale).round() / scale + 0.)
+}
+
+pub fn color_to_format(c: &Rgba) -> Value {
+        let formats: Vec<(&str, FormatFn)> = vec![
             ("hex", |x: &Rgb| format!("{:x}", x)),
             ("hex_r", |x: &Rgb| format!("{:0>2x}", x.red)),
             ("hex_g", |x: &Rgb| format!("{:0>2x}", x.green)),
@@ -196,10 +452,55 @@ pub fn color_to_format(c: &Rgb) -> Value {
             ("dec_r", |x: &Rgb| format!("{}", x.red as f64 / 255.)),
             ("dec_g", |x: &Rgb| format!("{}", x.green as f64 / 255.)),
             ("dec_b", |x: &Rgb| format!("{}", x.blue as f64 / 255.)),
+            ("hex_upper", |x: &Rgb| format!("{:X}", x)),
+            ("hash_hex", |x: &Rgb| format!("#{:x}", x)),
+            ("hex_0x", |x: &Rgb| format!("0x{:x}", x)),
+            ("rgb_slash", |x: &Rgb| format!("{:0>2x}/{:0>2x}/{:0>2x}", x.red, x.green, x.blue)),
+            ("rgb_css", |x: &Rgb| format!("rgb({}, {}, {})", x.red, x.green, x.blue)),
+            ("int_rgb", |x: &Rgb| format!("{}", u32::from_be_bytes([0, x.red, x.green, x.blue]))),
+            ("int_bgr", |x: &Rgb| format!("{}", u32::from_be_bytes([0, x.blue, x.green, x.red]))),
+            ("hsl_h", |x: &Rgb| round(to_hsl(x).hue.to_positive_degrees(), 1)),
+            ("hsl_s", |x: &Rgb| round(to_hsl(x).saturation * 100., 1)),
+            ("hsl_l", |x: &Rgb| round(to_hsl(x).lightness * 100., 1)),
+            ("hsl_css", |x: &Rgb| {
+                let hsl = to_hsl(x);
+                format!("hsl({}, {}%, {}%)",
+                    round(hsl.hue.to_positive_degrees(), 1),
+                    round(hsl.saturation * 100., 1),
+                    round(hsl.lightness * 100., 1))
+            }),
+            ("hsv_h", |x: &Rgb| round(to_hsv(x).hue.to_positive_degrees(), 1)),
+            ("hsv_s", |x: &Rgb| round(to_hsv(x).saturation * 100., 1)),
+            ("hsv_v", |x: &Rgb| round(to_hsv(x).value * 100., 1)),
+            ("hsv_css", |x: &Rgb| {
+                let hsv = to_hsv(x);
+                format!("hsv({}, {}%, {}%)",
+                    round(hsv.hue.to_positive_degrees(), 1),
+                    round(hsv.saturation * 100., 1),
+                    round(hsv.value * 100., 1))
+            }),
+            ("oklch_css", |x: &Rgb| {
+                let oklch: ext_palette::Oklch = x.into_format().into_color();
+                format!("oklch({} {} {})",
+                    round(oklch.l, 3),
+                    round(oklch.chroma, 3),
+                    round(oklch.hue.to_positive_degrees(), 1))
+            }),
+            ("ansi256", |x: &Rgb| format!("{}", color_science::ansi256(x))),
+            ("ansi16", |x: &Rgb| format!("{}", color_science::ansi16(x))),
+        ];
+        let alpha_formats: Vec<(&str, AlphaFormatFn)> = vec![
+            ("hex_a", |x: &Rgba| format!("{:0>2x}", x.alpha)),
+            ("int_a", |x: &Rgba| format!("{}", x.alpha)),
+            ("dec_a", |x: &Rgba| format!("{}", x.alpha as f64 / 255.)),
+            ("hex8", |x: &Rgba| format!("{:x}{:0>2x}", x.color, x.alpha)),
         ];
         let mut data_map: Map<String, Value> = Map::new();
         for (name, f) in formats {
-            data_map.insert(name.to_string().into(), f(c).into());
+            data_map.insert(name.to_string(), f(&c.color).into());
+        }
+        for (name, f) in alpha_formats {
+            data_map.insert(name.to_string(), f(c).into());
         }
         Value::Object(data_map)
 }
@@ -209,14 +510,42 @@ pub(crate) fn is_dark(config: &Config) -> bool {
     bg.l < fg.l
 }
 
+/// Contrast of `c` against `bg`, as seen when `c` is transparent.
+fn contrast_to_format(c: &Rgba, bg: &Rgb) -> [(&'static str, Value); 2] {
+    let c = color_science::blend(&c.color, bg, c.alpha as f32 / 255.);
+    [
+        ("contrast_bg", round(contrast::wcag_ratio(&c, bg), 2).into()),
+        ("apca_bg", round(contrast::apca_lc(&c, bg), 1).into()),
+    ]
+}
+
 pub(crate) fn format_variables(config: &Config, color_map: &Rc<RefCell<ColorMap>>) -> Value {
-    let mut colors = map_color_map(color_map, color_to_format);
+    let bg = config.palette.colors[0];
+    let format = |c: &Rgba| {
+        let mut formatted = color_to_format(c);
+        let mapping = formatted.as_object_mut().unwrap();
+        for (name, value) in contrast_to_format(c, &bg) {
+            mapping.insert(name.into(), value);
+        }
+        formatted
+    };
+    let mut colors = map_color_map(color_map, &format);
     let mut list = Vec::<Value>::new();
-    list_color_map(&mut list, &mut Vec::<String>::new(), color_map, color_to_format);
+    list_color_map(&mut list, &mut Vec::<String>::new(), color_map, &format);
     let mapping = colors.as_object_mut().unwrap();
     let list = Vec::from(&list[1..(list.len()-1)]);
     mapping.insert("PROGRAMMABLE".into(), Value::Array(list));
 
     mapping.insert("PALETTE".into(), config.palette.colors.map(|x| format!("{:x}", x)).join("-").into());
+    let is_dark = is_dark(config);
+    mapping.insert("IS_DARK".into(), is_dark.into());
+    mapping.insert("IS_LIGHT".into(), (!is_dark).into());
+    mapping.insert("VARIANT".into(), if is_dark { "dark" } else { "light" }.into());
+    let metadata = [("NAME", config.name.clone()), ("SLUG", config.slug()), ("AUTHOR", config.author.clone())];
+    for (key, value) in metadata {
+        if let Some(value) = value {
+            mapping.insert(key.into(), value.into());
+        }
+    }
     colors
 }
@@ -1,18 +1,25 @@
+#[cfg(test)]
 use std::str::FromStr;
 
+use std::sync::OnceLock;
 use itertools::Itertools;
-use ext_palette::rgb::channels::Argb;
-use ext_palette::{Srgb, Xyz, Lab, convert::IntoColorUnclamped, IntoColor, Lch};
+use ext_palette::{Srgb, LinSrgb, Xyz, Lab, Oklab, Oklch, convert::IntoColorUnclamped, IntoColor, Lch};
+#[cfg(test)]
+use ext_palette::FromColor;
+use serde::{Serialize, Deserialize};
 
-use crate::{Color, palette::Palette};
+#[cfg(test)]
+use crate::palette::Palette;
+use crate::config::Absolute;
 
 pub type Rgb = Srgb<u8>;
 
 pub fn mix1d(a: f32, b: f32, w: f32) -> f32 {
-    return a*(1.-w)+b*w;
+    a*(1.-w)+b*w
 }
 
-pub fn mix(c1: &Rgb, c2: &Rgb, w: f32) -> Rgb {
+/// Returns the color and whether it was out of gamut, see `gamut_map`.
+pub fn mix(c1: &Rgb, c2: &Rgb, w: f32) -> (Rgb, bool) {
     let c1_xyz: Xyz = c1.into_format().into_color_unclamped();
     let c2_xyz: Xyz = c2.into_format().into_color_unclamped();
     let c1_lab: Lab = c1_xyz.into_color_unclamped();
@@ -24,23 +31,221 @@ pub fn mix(c1: &Rgb, c2: &Rgb, w: f32) -> Rgb {
     let c3l_lab: Lab = Xyz::new(0., c3y, 0.).into_color_unclamped();
     let c3 = Lab::new(c3l_lab.l, mix1d(c1_lab.a, c2_lab.a, w), mix1d(c1_lab.b, c2_lab.b, w));
 
-    let c3: Srgb = c3.into_color_unclamped();
-    c3.into_format()
+    gamut_map(c3.into_color_unclamped())
+}
+
+/// Color space in which shades and `mix()` interpolate.
+#[derive(Debug, Default, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorSpace {
+    /// Lab a/b, with lightness following the log of luminance so contrast changes evenly. See `mix`.
+    #[default]
+    LabContrast,
+    Oklab,
+    /// OKLCH, the hue goes the shorter way and mostly follows the more colorful color.
+    Oklch,
+    LinearSrgb,
+}
+
+/// Moves `c1` toward `c2` by `w` in `color_space`. `w` outside of 0 to 1 extrapolates.
+///
+/// Returns the color and whether it was out of gamut, see `gamut_map`.
+pub fn mix_in(color_space: ColorSpace, c1: &Rgb, c2: &Rgb, w: f32) -> (Rgb, bool) {
+    let c: Oklch = match color_space {
+        ColorSpace::LabContrast => return mix(c1, c2, w),
+        ColorSpace::Oklab => {
+            let c1: Oklab = c1.into_format().into_color();
+            let c2: Oklab = c2.into_format().into_color();
+            Oklab::new(mix1d(c1.l, c2.l, w), mix1d(c1.a, c2.a, w), mix1d(c1.b, c2.b, w)).into_color_unclamped()
+        },
+        ColorSpace::Oklch => {
+            let c1: Oklch = c1.into_format().into_color();
+            let c2: Oklch = c2.into_format().into_color();
+            let h1 = c1.hue.to_positive_degrees();
+            let diff = (c2.hue - c1.hue).to_degrees();
+            // Weighting the hue by chroma keeps greys from pulling the hue of colorful colors.
+            let hue_w = match (1. - w.clamp(0., 1.)) * c1.chroma + w.clamp(0., 1.) * c2.chroma {
+                total if total > 0. => w.clamp(0., 1.) * c2.chroma / total,
+                _ => w.clamp(0., 1.),
+            };
+            let chroma = mix1d(c1.chroma, c2.chroma, w).max(0.);
+            Oklch::new(mix1d(c1.l, c2.l, w), chroma, h1 + diff * hue_w)
+        },
+        ColorSpace::LinearSrgb => {
+            let c1 = c1.into_format::<f32>().into_linear();
+            let c2 = c2.into_format::<f32>().into_linear();
+            LinSrgb::new(
+                mix1d(c1.red, c2.red, w),
+                mix1d(c1.green, c2.green, w),
+                mix1d(c1.blue, c2.blue, w),
+            ).into_color_unclamped()
+        },
+    };
+    gamut_map(c)
+}
+
+/// Converts `c` to sRGB. Out of gamut colors have their chroma reduced at
+/// constant lightness and hue until clipping them is barely noticeable, as in
+/// CSS Color 4.
+///
+/// Also returns whether `c` was out of gamut.
+pub fn gamut_map(c: Oklch) -> (Rgb, bool) {
+    // Just noticeable difference in OKLab.
+    const JND: f32 = 0.02;
+    const EPSILON: f32 = 0.0001;
+    // Half a step of `u8`, so colors only changed by rounding aren't out of gamut.
+    const TOLERANCE: f32 = 0.5 / 255.;
+
+    let in_gamut = |c: &Srgb| [c.red, c.green, c.blue].iter().all(|x| (-TOLERANCE..=1. + TOLERANCE).contains(x));
+    let clip = |c: Oklch| {
+        let c: Srgb = c.into_color_unclamped();
+        Srgb::new(c.red.clamp(0., 1.), c.green.clamp(0., 1.), c.blue.clamp(0., 1.))
+    };
+    let delta_eok = |c1: &Srgb, c2: Oklch| {
+        let c1: Oklab = (*c1).into_color_unclamped();
+        let c2: Oklab = c2.into_color_unclamped();
+        ((c1.l - c2.l).powi(2) + (c1.a - c2.a).powi(2) + (c1.b - c2.b).powi(2)).sqrt()
+    };
+
+    let srgb: Srgb = c.into_color_unclamped();
+    if in_gamut(&srgb) {
+        return (srgb.into_format(), false);
+    }
+    if c.l >= 1. {
+        return (Rgb::new(255, 255, 255), true);
+    }
+    if c.l <= 0. {
+        return (Rgb::new(0, 0, 0), true);
+    }
+    let mut clipped = clip(c);
+    if delta_eok(&clipped, c) < JND {
+        return (clipped.into_format(), true);
+    }
+    let (mut min, mut max) = (0., c.chroma);
+    let mut min_in_gamut = true;
+    let mut current = c;
+    while max - min > EPSILON {
+        current.chroma = (min + max) / 2.;
+        if min_in_gamut && in_gamut(&current.into_color_unclamped()) {
+            min = current.chroma;
+            continue;
+        }
+        clipped = clip(current);
+        let e = delta_eok(&clipped, current);
+        if e < JND {
+            if JND - e < EPSILON {
+                break;
+            }
+            min_in_gamut = false;
+            min = current.chroma;
+        } else {
+            max = current.chroma;
+        }
+    }
+    (clipped.into_format(), true)
+}
+
+/// Changes the Lab lightness of `c` by `amount`, 1 being the whole range.
+///
+/// Returns the color and whether it was out of gamut, see `gamut_map`.
+pub fn lighten(c: &Rgb, amount: f32) -> (Rgb, bool) {
+    let mut lab: Lab = c.into_format().into_color();
+    lab.l += amount * 100.;
+    gamut_map(lab.into_color_unclamped())
+}
+
+/// Blends `c` with opacity `alpha` onto `bg`.
+pub fn blend(c: &Rgb, bg: &Rgb, alpha: f32) -> Rgb {
+    let c: Srgb = c.into_format();
+    let bg: Srgb = bg.into_format();
+    Srgb::new(
+        mix1d(bg.red, c.red, alpha),
+        mix1d(bg.green, c.green, alpha),
+        mix1d(bg.blue, c.blue, alpha),
+    ).into_format()
+}
+
+/// Default colors of xterm for the 16 ANSI indices.
+const XTERM_16: [(u8, u8, u8); 16] = [
+    (0x00, 0x00, 0x00), (0xcd, 0x00, 0x00), (0x00, 0xcd, 0x00), (0xcd, 0xcd, 0x00),
+    (0x00, 0x00, 0xee), (0xcd, 0x00, 0xcd), (0x00, 0xcd, 0xcd), (0xe5, 0xe5, 0xe5),
+    (0x7f, 0x7f, 0x7f), (0xff, 0x00, 0x00), (0x00, 0xff, 0x00), (0xff, 0xff, 0x00),
+    (0x5c, 0x5c, 0xff), (0xff, 0x00, 0xff), (0x00, 0xff, 0xff), (0xff, 0xff, 0xff),
+];
+
+/// Color of xterm-256 index `i`, for the 6x6x6 cube (16-231) and the greyscale ramp (232-255).
+fn xterm_256_color(i: u8) -> Rgb {
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    match i {
+        0..=15 => Rgb::from(XTERM_16[i as usize]),
+        16..=231 => {
+            let i = (i - 16) as usize;
+            Rgb::new(LEVELS[i / 36], LEVELS[i / 6 % 6], LEVELS[i % 6])
+        },
+        232..=255 => Rgb::new(8 + 10 * (i - 232), 8 + 10 * (i - 232), 8 + 10 * (i - 232)),
+    }
+}
+
+/// Index among `indices` whose xterm color is closest to `c` in Lab.
+fn nearest_xterm_index(c: &Rgb, indices: std::ops::RangeInclusive<u8>) -> u8 {
+    static XTERM_LAB: OnceLock<Vec<Lab>> = OnceLock::new();
+    let xterm_lab = XTERM_LAB.get_or_init(|| (0..=255).map(|i| xterm_256_color(i).into_format().into_color()).collect());
+    let lab: Lab = c.into_format().into_color();
+    indices.map(|i| {
+        let other = xterm_lab[i as usize];
+        (i, (lab.l - other.l).powi(2) + (lab.a - other.a).powi(2) + (lab.b - other.b).powi(2))
+    }).min_by(|a, b| a.1.total_cmp(&b.1)).unwrap().0
+}
+
+/// Nearest xterm-256 index, from the color cube or the greyscale ramp.
+///
+/// Indices 0-15 are skipped because terminals let users change them.
+pub fn ansi256(c: &Rgb) -> u8 {
+    nearest_xterm_index(c, 16..=255)
+}
+
+/// Nearest of the 16 ANSI indices, assuming the default xterm colors.
+pub fn ansi16(c: &Rgb) -> u8 {
+    nearest_xterm_index(c, 0..=15)
 }
 
 #[derive(Debug)]
 pub struct ColorNameWeight {
     pub color: Rgb,
-    pub name: &'static str,
+    pub name: String,
     pub weight: f32,
 }
 
 impl ColorNameWeight {
-    fn new(hex: &str, name: &'static str, weight: f32) -> ColorNameWeight {
+    fn new(color: Rgb, name: &str, weight: f32) -> ColorNameWeight {
         ColorNameWeight {
-            color: Rgb::from_str(hex).unwrap(),
-            name: name,
-            weight: 9.
+            color,
+            name: name.to_string(),
+            weight,
+        }
+    }
+}
+
+/// How far a palette color is from an absolute color goal.
+#[derive(Debug, Default, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    /// RGB distance after moving both color sets to the same average. See `score2`.
+    #[default]
+    RgbOffset,
+    /// LCH distance after removing the average of the palette colors. See `score`.
+    Lch,
+    /// CIEDE2000 after moving both color sets to the same average. See `score_ciede2000`.
+    Ciede2000,
+}
+
+impl Metric {
+    /// Weights of the 3 channels (r, g, b), (l, c, h) or (l, c, h) when the config doesn't set them.
+    pub fn default_channel_weight(&self) -> [f32;3] {
+        match self {
+            Metric::RgbOffset => [1., 1., 1.],
+            Metric::Lch => [0., 1., 6.],
+            Metric::Ciede2000 => [1., 1., 1.],
         }
     }
 }
@@ -61,85 +266,223 @@ pub fn average_color2(colors: &[Rgb]) -> Lab {
     rtn
 }
 
-pub fn average_color(colors: &[Rgb]) -> Lab {
-    let mut mut_average = Lab::new(0.,0.,0.);
-    for c in colors {
-        let lab: Lab = c.into_format().into_color();
-        mut_average += lab;
-    }
-    let len = colors.len() as f32;
-    mut_average /= len;
-    mut_average
+/// Assigns a color to every target so that the sum of `f` is minimal.
+pub fn min_order_by<F>(colors: &[Rgb], target: &[ColorNameWeight], mut f: F) -> Vec<ColorNameWeight>
+where F: FnMut(&Rgb, &ColorNameWeight) -> f32 {
+    let order = assign(colors.len(), target.len(), |cr, ca| f(&colors[cr], &target[ca]));
+    order.iter().zip_eq(target.iter()).map(|(c, cnw)| {
+        ColorNameWeight::new(colors[*c], &cnw.name, cnw.weight)
+    }).collect()
 }
 
-pub fn minus_colors(colors: &[Rgb], average: Lab) -> [Lab; 7] {
-    colors.iter().map(|c| {
-        let lab: Lab = c.into_format().into_color();
-        lab - average
-    }).collect::<Vec<Lab>>().try_into().unwrap()
-} 
+/// Returns the index of the color assigned to each target, minimizing the sum
+/// of `cost(color_index, target_index)`.
+///
+/// Each color is used at most once. When there are more targets than colors,
+/// every color gets a distinct target first and the leftover targets take
+/// their closest color.
+fn assign<F>(color_count: usize, target_count: usize, mut cost: F) -> Vec<usize>
+where F: FnMut(usize, usize) -> f32 {
+    // NaN would break the comparisons of the solver.
+    let mut cost = |cr, ca| {
+        let c = cost(cr, ca);
+        if c.is_nan() { f32::MAX } else { c }
+    };
+    if target_count <= color_count {
+        let matrix: Vec<Vec<f32>> = (0..target_count)
+            .map(|ca| (0..color_count).map(|cr| cost(cr, ca)).collect())
+            .collect();
+        return min_cost_assignment(&matrix);
+    }
+    let matrix: Vec<Vec<f32>> = (0..color_count)
+        .map(|cr| (0..target_count).map(|ca| cost(cr, ca)).collect())
+        .collect();
+    let mut order = vec![None; target_count];
+    for (cr, ca) in min_cost_assignment(&matrix).into_iter().enumerate() {
+        order[ca] = Some(cr);
+    }
+    order.into_iter().enumerate().map(|(ca, cr)| cr.unwrap_or_else(|| {
+        (0..color_count).min_by(|a, b| matrix[*a][ca].total_cmp(&matrix[*b][ca])).unwrap()
+    })).collect()
+}
 
-pub fn min_order_by<F>(colors: &[Rgb], target: &[ColorNameWeight;6], mut f: F) -> [ColorNameWeight;6]
-where F: FnMut(&Rgb, &ColorNameWeight) -> f32 {
-    let result = colors.into_iter().permutations(target.len()).min_by_key(|perm| {
-            let mut sum: f32 = 0.;
-            for (ca, cr) in target.iter().zip_eq(perm) {
-                sum += f(cr, ca);
-            };
-            (sum * 1000.) as i64
-    });
-    result.unwrap().iter().zip_eq(target.iter()).map(|(c, cnw)| {
-        ColorNameWeight::new(&format!("{:x}", **c), cnw.name, cnw.weight)
-    }).collect::<Vec<ColorNameWeight>>().try_into().unwrap()
+/// Hungarian (Kuhn-Munkres) algorithm in O(n^2 * m).
+///
+/// `cost` is a `n x m` matrix with `n <= m`. Returns the distinct column
+/// assigned to each row so that the sum of the costs is minimal.
+fn min_cost_assignment(cost: &[Vec<f32>]) -> Vec<usize> {
+    let n = cost.len();
+    if n == 0 {
+        return Vec::new();
+    }
+    let m = cost[0].len();
+    // Rows and columns are 1-indexed, column 0 is a virtual column used to
+    // start augmenting paths.
+    let mut u = vec![0f64; n + 1];
+    let mut v = vec![0f64; m + 1];
+    // Row currently matched to each column.
+    let mut row_of = vec![0usize; m + 1];
+    let mut way = vec![0usize; m + 1];
+    for i in 1..=n {
+        row_of[0] = i;
+        let mut j0 = 0;
+        let mut min_v = vec![f64::INFINITY; m + 1];
+        let mut used = vec![false; m + 1];
+        loop {
+            used[j0] = true;
+            let i0 = row_of[j0];
+            let mut delta = f64::INFINITY;
+            let mut j1 = 0;
+            for j in 1..=m {
+                if used[j] { continue; }
+                let cur = cost[i0 - 1][j - 1] as f64 - u[i0] - v[j];
+                if cur < min_v[j] {
+                    min_v[j] = cur;
+                    way[j] = j0;
+                }
+                if min_v[j] < delta {
+                    delta = min_v[j];
+                    j1 = j;
+                }
+            }
+            for j in 0..=m {
+                if used[j] {
+                    u[row_of[j]] += delta;
+                    v[j] -= delta;
+                } else {
+                    min_v[j] -= delta;
+                }
+            }
+            j0 = j1;
+            if row_of[j0] == 0 { break; }
+        }
+        while j0 != 0 {
+            let j1 = way[j0];
+            row_of[j0] = row_of[j1];
+            j0 = j1;
+        }
+    }
+    let mut rtn = vec![0; n];
+    for (j, i) in row_of.into_iter().enumerate().skip(1) {
+        if i != 0 {
+            rtn[i - 1] = j - 1;
+        }
+    }
+    rtn
 }
 
-pub fn score2(cr: &Rgb, caa: &ColorNameWeight, average: Lab, average2: Lab) -> f32 {
+pub fn score2(cr: &Rgb, caa: &ColorNameWeight, average: Lab, average2: Lab, weight: [f32;3]) -> f32 {
     let ca: Srgb<f32> = caa.color.into_format();
     let cr: Srgb<f32> = cr.into_format();
     let average: Srgb<f32> = average.into_color();
     let average2: Srgb<f32> = average2.into_color();
     let ca = Srgb::new(ca.red - average2.red, ca.green - average2.green, ca.blue - average2.blue);
     let cr = Srgb::new(cr.red - average.red, cr.green - average.green, cr.blue - average.blue);
-    // println!("cr: {:?}", cr);
-    // println!("ca: {:?}", ca);
     let ds = ((ca.red - cr.red), (ca.green - cr.green), (ca.blue - cr.blue));
-    let weight = (1., 1., 1.);
-    let tmp_sum = (ds.0 * ds.0 * weight.0 + ds.1*ds.1 * weight.1 + ds.2*ds.2*weight.2).sqrt();
+    let tmp_sum = (ds.0 * ds.0 * weight[0] + ds.1*ds.1 * weight[1] + ds.2*ds.2*weight[2]).sqrt();
     tmp_sum * caa.weight
 }
 
-pub fn score(cr: &Rgb, caa: &ColorNameWeight, average: Lab) -> f32 {
+pub fn score(cr: &Rgb, caa: &ColorNameWeight, average: Lab, weight: [f32;3]) -> f32 {
         let ca: Lch = caa.color.into_format().into_color();
         let cr: Lab = cr.into_format().into_color();
         let cr: Lab = cr - average;
-        println!("cr: {:?}", cr);
         let cr: Lch = cr.into_color_unclamped();
-        println!("cr: {:?}", cr);
-        println!("ca: {:?}", ca);
         let ds = ((ca.l - cr.l), (ca.chroma - cr.chroma), (ca.hue - cr.hue).to_degrees());
-        println!("ds: {:?}", ds);
-        let weight = (0., 1., 6.);
-        let tmp_sum = (ds.0 * ds.0 * weight.0 + ds.1*ds.1 * weight.1 + ds.2*ds.2*weight.2).sqrt();
+        let tmp_sum = (ds.0 * ds.0 * weight[0] + ds.1*ds.1 * weight[1] + ds.2*ds.2*weight[2]).sqrt();
         tmp_sum * caa.weight
 }
 
-pub fn get_matching_absolute_color(colors: &[Rgb]) -> Vec<ColorNameWeight> {
-    let absolute_colors: [ColorNameWeight;6] = [
-        ColorNameWeight::new("ff0000", "red", 9.),
-        ColorNameWeight::new("ffff00", "yellow", 9.),
-        ColorNameWeight::new("00ff00", "green", 9.),
-        ColorNameWeight::new("00ffff", "cyan", 1.),
-        ColorNameWeight::new("0000ff", "blue", 1.),
-        ColorNameWeight::new("ff00ff", "magenta", 1.),
-    ];
+pub fn score_ciede2000(cr: &Rgb, caa: &ColorNameWeight, average: Lab, average2: Lab, weight: [f32;3]) -> f32 {
+    let ca: Lab = caa.color.into_format().into_color();
+    let cr: Lab = cr.into_format().into_color();
+    let cr = cr - average + average2;
+    ciede2000(&cr, &ca, weight) * caa.weight
+}
+
+/// CIEDE2000 color difference.
+///
+/// `weight` multiplies the squared lightness, chroma and hue terms, so `[1., 1., 1.]`
+/// is the standard formula and `1 / k^2` maps to the parametric factors `kL`, `kC` and `kH`.
+pub fn ciede2000(lab1: &Lab, lab2: &Lab, weight: [f32;3]) -> f32 {
+    let (l1, a1, b1) = (lab1.l as f64, lab1.a as f64, lab1.b as f64);
+    let (l2, a2, b2) = (lab2.l as f64, lab2.a as f64, lab2.b as f64);
+    let pow25_7 = 25f64.powi(7);
+
+    let c_bar = ((a1 * a1 + b1 * b1).sqrt() + (a2 * a2 + b2 * b2).sqrt()) / 2.;
+    let g = 0.5 * (1. - (c_bar.powi(7) / (c_bar.powi(7) + pow25_7)).sqrt());
+    let a1p = a1 * (1. + g);
+    let a2p = a2 * (1. + g);
+    let c1p = (a1p * a1p + b1 * b1).sqrt();
+    let c2p = (a2p * a2p + b2 * b2).sqrt();
+    let hue = |b: f64, a: f64| if b == 0. && a == 0. { 0. } else { b.atan2(a).to_degrees().rem_euclid(360.) };
+    let h1p = hue(b1, a1p);
+    let h2p = hue(b2, a2p);
 
+    let dl = l2 - l1;
+    let dc = c2p - c1p;
+    let dh = if c1p * c2p == 0. {
+        0.
+    } else if h2p - h1p > 180. {
+        h2p - h1p - 360.
+    } else if h2p - h1p < -180. {
+        h2p - h1p + 360.
+    } else {
+        h2p - h1p
+    };
+    let dh = 2. * (c1p * c2p).sqrt() * (dh / 2.).to_radians().sin();
+
+    let l_bar = (l1 + l2) / 2.;
+    let c_bar = (c1p + c2p) / 2.;
+    let h_bar = if c1p * c2p == 0. {
+        h1p + h2p
+    } else if (h1p - h2p).abs() <= 180. {
+        (h1p + h2p) / 2.
+    } else if h1p + h2p < 360. {
+        (h1p + h2p + 360.) / 2.
+    } else {
+        (h1p + h2p - 360.) / 2.
+    };
+
+    let t = 1. - 0.17 * (h_bar - 30.).to_radians().cos()
+        + 0.24 * (2. * h_bar).to_radians().cos()
+        + 0.32 * (3. * h_bar + 6.).to_radians().cos()
+        - 0.20 * (4. * h_bar - 63.).to_radians().cos();
+    let d_theta = 30. * (-((h_bar - 275.) / 25.).powi(2)).exp();
+    let r_c = 2. * (c_bar.powi(7) / (c_bar.powi(7) + pow25_7)).sqrt();
+    let s_l = 1. + 0.015 * (l_bar - 50.).powi(2) / (20. + (l_bar - 50.).powi(2)).sqrt();
+    let s_c = 1. + 0.045 * c_bar;
+    let s_h = 1. + 0.015 * c_bar * t;
+    let r_t = -(2. * d_theta).to_radians().sin() * r_c;
+
+    let dl = dl / s_l * (weight[0] as f64).sqrt();
+    let dc = dc / s_c * (weight[1] as f64).sqrt();
+    let dh = dh / s_h * (weight[2] as f64).sqrt();
+    (dl * dl + dc * dc + dh * dh + r_t * dc * dh).max(0.).sqrt() as f32
+}
+
+/// Names each goal of `absolute` after its closest color in `colors`.
+pub fn get_matching_absolute_color(colors: &[Rgb], absolute: &Absolute) -> Vec<ColorNameWeight> {
+    if absolute.goal.is_empty() {
+        return Vec::new();
+    }
+    let absolute_colors: Vec<ColorNameWeight> = absolute.goal.iter()
+        .map(|(name, color)| ColorNameWeight::new(*color, name, absolute.weight.get(name).copied().unwrap_or(1.)))
+        .collect();
+
+    let metric = absolute.metric;
+    let weight = absolute.channel_weight.unwrap_or_else(|| metric.default_channel_weight());
     let average = average_color2(colors);
     let average2 = average_color2(&absolute_colors.iter().map(|cnw| cnw.color).collect::<Vec<Rgb>>());
-    let result = min_order_by(colors, &absolute_colors, |cr, caa| score2(cr, caa, average, average2));
-    result.into()
+    min_order_by(colors, &absolute_colors, |cr, caa| match metric {
+        Metric::RgbOffset => score2(cr, caa, average, average2, weight),
+        Metric::Lch => score(cr, caa, average, weight),
+        Metric::Ciede2000 => score_ciede2000(cr, caa, average, average2, weight),
+    })
 }
 
-pub fn closest_order<'a, 'b>(colors: &'a[Rgb], target: &'b [(Rgb, f32)]) -> Vec<&'a Rgb> {
+#[allow(dead_code)]
+pub fn closest_order<'a>(colors: &'a[Rgb], target: &[(Rgb, f32)]) -> Vec<&'a Rgb> {
         let average = {
           let mut mut_average: (f32, f32, f32) = (0.,0.,0.);
             for c in colors {
@@ -154,32 +497,27 @@ pub fn closest_order<'a, 'b>(colors: &'a[Rgb], target: &'b [(Rgb, f32)]) -> Vec<
             mut_average
         };
 
-        let result = colors.into_iter().permutations(target.len()).min_by_key(|perm| {
-            let mut sum: f32 = 0.;
-            for (ca, cr) in target.iter().zip_eq(perm) {
-                let weight2 = ca.1;
-                let ca: Lab = ca.0.into_format().into_color();
-                let mut cr: Lab = cr.into_format().into_color();
-                cr.a-=average.1;
-                cr.b-=average.2;
-                let ca: Lch = ca.into_color();
-                let cr: Lch = cr.into_color();
-                let ds = ((ca.l - cr.l), (ca.chroma - cr.chroma), (ca.hue - cr.hue).to_degrees());
-                let weight = (0., 1., 6.);
-                let tmp_sum = (ds.0 * ds.0 * weight.0 + ds.1*ds.1 * weight.1 + ds.2*ds.2*weight.2).sqrt();
-                sum += tmp_sum * weight2;
-            }
+        let order = assign(colors.len(), target.len(), |cr, ca| {
+            let weight2 = target[ca].1;
+            let ca: Lab = target[ca].0.into_format().into_color();
+            let mut cr: Lab = colors[cr].into_format().into_color();
+            cr.a-=average.1;
+            cr.b-=average.2;
+            let ca: Lch = ca.into_color();
+            let cr: Lch = cr.into_color();
+            let ds = ((ca.l - cr.l), (ca.chroma - cr.chroma), (ca.hue - cr.hue).to_degrees());
+            let weight = (0., 1., 6.);
+            let tmp_sum = (ds.0 * ds.0 * weight.0 + ds.1*ds.1 * weight.1 + ds.2*ds.2*weight.2).sqrt();
+            tmp_sum * weight2
+        });
 
-            (sum * 1000.) as i64
-        }).unwrap();
-        
-        result.iter().map(|x| *x).collect()
+        order.into_iter().map(|i| &colors[i]).collect()
 }
 
 #[test]
 #[ignore]
 fn tmp() {
-    let palette = Palette::from_str("1d2021-d5c4a1-fb4934-fabd2f-b8bb26-8ec07c-83a598-d3869b-fe8019");
+    let _palette = Palette::from_str("1d2021-d5c4a1-fb4934-fabd2f-b8bb26-8ec07c-83a598-d3869b-fe8019");
     let colors = [
        Rgb::from_str("ff5555").unwrap(),
        Rgb::from_str("f1fa8c").unwrap(),
@@ -190,17 +528,116 @@ fn tmp() {
        Rgb::from_str("ffb86c").unwrap(),
     ];
     let absolute_colors: [ColorNameWeight;6] = [
-        ColorNameWeight::new("ff0000", "red", 1.),
-        ColorNameWeight::new("ffff00", "yellow", 1.),
-        ColorNameWeight::new("00ff00", "green", 1.),
-        ColorNameWeight::new("00ffff", "cyan", 1.),
-        ColorNameWeight::new("0000ff", "blue", 1.),
-        ColorNameWeight::new("ff00ff", "magenta", 1.),
+        ColorNameWeight::new(Rgb::from_str("ff0000").unwrap(), "red", 1.),
+        ColorNameWeight::new(Rgb::from_str("ffff00").unwrap(), "yellow", 1.),
+        ColorNameWeight::new(Rgb::from_str("00ff00").unwrap(), "green", 1.),
+        ColorNameWeight::new(Rgb::from_str("00ffff").unwrap(), "cyan", 1.),
+        ColorNameWeight::new(Rgb::from_str("0000ff").unwrap(), "blue", 1.),
+        ColorNameWeight::new(Rgb::from_str("ff00ff").unwrap(), "magenta", 1.),
     ];
     let average = average_color2(&colors);
     let average2 = average_color2(&absolute_colors.iter().map(|cnw| cnw.color).collect::<Vec<Rgb>>());
     // println!("rr: {:?}", score2(&colors[0], &absolute_colors[0], average, average2));
     // println!("or: {:?}", score2(&colors[6], &absolute_colors[0], average, average2));
-    println!("yy: {:?}", score2(&colors[1], &absolute_colors[1], average, average2));
-    println!("oy: {:?}", score2(&colors[6], &absolute_colors[1], average, average2));
-}
\ No newline at end of file
+    println!("yy: {:?}", score2(&colors[1], &absolute_colors[1], average, average2, [1., 1., 1.]));
+    println!("oy: {:?}", score2(&colors[6], &absolute_colors[1], average, average2, [1., 1., 1.]));
+}
+#[test]
+fn min_cost_assignment_matches_brute_force() {
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+    let mut rng = StdRng::seed_from_u64(0);
+    for _ in 0..200 {
+        let n = rng.gen_range(1..=6);
+        let m = rng.gen_range(n..=7);
+        let cost: Vec<Vec<f32>> = (0..n).map(|_| (0..m).map(|_| rng.gen_range(0f32..100f32)).collect()).collect();
+        let sum = |order: &[usize]| order.iter().enumerate().map(|(i, j)| cost[i][*j]).sum::<f32>();
+
+        let order = min_cost_assignment(&cost);
+        assert_eq!(order.iter().unique().count(), n);
+        let best = (0..m).permutations(n).map(|perm| sum(&perm)).fold(f32::INFINITY, f32::min);
+        assert!((sum(&order) - best).abs() < 1e-3, "{} != {}", sum(&order), best);
+    }
+}
+
+#[test]
+fn ciede2000_works() {
+    // from "The CIEDE2000 Color-Difference Formula" test data by Sharma et al.
+    let data = [
+        ((50., 2.6772, -79.7751), (50., 0., -82.7485), 2.0425),
+        ((50., -1.3802, -84.2814), (50., 0., -82.7485), 1.0000),
+        ((50., 2.5, 0.), (50., 0., -2.5), 4.3065),
+        ((60.2574, -34.0099, 36.2677), (60.4626, -34.1751, 39.4387), 1.2644),
+        ((2.0776, 0.0795, -1.1350), (0.9033, -0.0636, -0.5514), 0.9082),
+    ];
+    for (lab1, lab2, expected) in data {
+        let lab1 = Lab::from_components(lab1);
+        let lab2 = Lab::from_components(lab2);
+        assert!((ciede2000(&lab1, &lab2, [1., 1., 1.]) - expected).abs() < 1e-3);
+        assert!((ciede2000(&lab2, &lab1, [1., 1., 1.]) - expected).abs() < 1e-3);
+    }
+}
+
+#[test]
+fn assign_scales_to_many_targets() {
+    let colors: Vec<Rgb> = ["ff5555", "f1fa8c", "50fa7b", "8be9fd", "bd93f9", "ff79c6", "ffb86c"]
+        .iter().map(|hex| Rgb::from_str(hex).unwrap()).collect();
+    let mut absolute = crate::Config::default().absolute;
+    absolute.goal = [
+        ("red", "ff0000"), ("orange", "ff8000"), ("yellow", "ffff00"), ("lime", "80ff00"),
+        ("green", "00ff00"), ("teal", "00ff80"), ("cyan", "00ffff"), ("azure", "0080ff"),
+        ("blue", "0000ff"), ("purple", "8000ff"), ("magenta", "ff00ff"), ("pink", "ff0080"),
+    ].iter().map(|(name, hex)| (name.to_string(), Rgb::from_str(hex).unwrap())).collect();
+    let result = get_matching_absolute_color(&colors, &absolute);
+    assert_eq!(result.len(), absolute.goal.len());
+    // every color is used before any color is shared
+    assert_eq!(result.iter().map(|cnw| cnw.color).unique_by(|c| format!("{:x}", c)).count(), colors.len());
+}
+
+#[test]
+fn ansi_indices_work() {
+    assert_eq!(ansi256(&Rgb::new(0, 0, 0)), 16);
+    assert_eq!(ansi256(&Rgb::new(255, 255, 255)), 231);
+    assert_eq!(ansi256(&Rgb::new(0xff, 0x87, 0x00)), 208);
+    assert_eq!(ansi256(&Rgb::new(0x80, 0x80, 0x80)), 244);
+    assert_eq!(ansi16(&Rgb::new(0xff, 0x20, 0x20)), 9);
+    assert_eq!(ansi16(&Rgb::new(0x20, 0x20, 0x20)), 0);
+    for i in 16..=255 {
+        assert_eq!(ansi256(&xterm_256_color(i)), i);
+    }
+}
+
+#[test]
+fn mix_in_works() {
+    let bg = Rgb::from_str("282936").unwrap();
+    let blue = Rgb::from_str("3050ff").unwrap();
+    for color_space in [ColorSpace::LabContrast, ColorSpace::Oklab, ColorSpace::Oklch, ColorSpace::LinearSrgb] {
+        for (w, expected) in [(0., bg), (1., blue)] {
+            let (actual, _) = mix_in(color_space, &bg, &blue, w);
+            let close = |a: u8, b: u8| (a as i16 - b as i16).abs() <= 1;
+            assert!(close(actual.red, expected.red) && close(actual.green, expected.green) && close(actual.blue, expected.blue),
+                "{:?} at {}: {:x} != {:x}", color_space, w, actual, expected);
+        }
+    }
+    // Shades of a saturated blue keep its hue in OKLCH.
+    let hue = |c: Rgb| Oklch::from_color(c.into_format::<f32>()).hue.to_positive_degrees();
+    for w in [0.25, 0.5, 0.75] {
+        let (shade, _) = mix_in(ColorSpace::Oklch, &bg, &blue, w);
+        assert!((hue(shade) - hue(blue)).abs() < 8., "{} {}", hue(shade), hue(blue));
+    }
+}
+
+#[test]
+fn gamut_map_works() {
+    let orange = Rgb::from_str("ff8800").unwrap();
+    assert_eq!(gamut_map(Oklch::from_color(orange.into_format::<f32>())), (orange, false));
+
+    let hue = |c: Rgb| Oklch::from_color(c.into_format::<f32>());
+    let bright_blue = Oklch::new(0.6, 0.4, 260.);
+    let (mapped, out_of_gamut) = gamut_map(bright_blue);
+    assert!(out_of_gamut);
+    assert!((hue(mapped).l - 0.6).abs() < 0.02, "{:?}", hue(mapped));
+    assert!((hue(mapped).hue.to_positive_degrees() - 260.).abs() < 3., "{:?}", hue(mapped));
+    assert!(hue(mapped).chroma < 0.4);
+
+    assert_eq!(gamut_map(Oklch::new(1.2, 0.1, 100.)), (Rgb::new(255, 255, 255), true));
+}
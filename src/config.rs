@@ -1,44 +1,161 @@
-use std::{collections::HashMap, fmt};
+use std::{fmt, path::Path, str::FromStr};
+use indexmap::IndexMap;
 use serde::{Serialize, Deserialize, de::{Visitor, self}, Deserializer};
+use serde_json::Value;
 
-use crate::palette::Palette;
+use crate::{Color, palette::Palette, color_science::{ColorSpace, Metric}, expression::{Expression, Function}, error::{Error, Result}};
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub palette: Palette,
-    pub shades: HashMap<String, f32>,
-    pub colors: HashMap<String, ColorNames>,
+    pub absolute: Absolute,
+    pub shades: IndexMap<String, f32>,
+    pub colors: IndexMap<String, ColorNames>,
+    /// Color space in which shades and `mix()` interpolate.
+    #[serde(default)]
+    pub color_space: ColorSpace,
+    #[serde(default)]
+    pub order: VariableOrder,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slug: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
 }
 
-static DEFAULT_CONFIG: &'static str = include_str!(concat!(env!("OUT_DIR"), "/default_config.json"));
+/// Order of the variables given to templates, e.g. in `PROGRAMMABLE`.
+#[derive(Debug, Default, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VariableOrder {
+    /// Same order as in config. Built in colors not in `colors` come last.
+    #[default]
+    Declaration,
+    Alphabetical,
+}
 
-impl Config {
-    pub fn default() -> Config {
+/// Colors named after the hue (`red`, `yellow`, ...) regardless of the order of `c1`...`c7`.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Absolute {
+    /// How the distance between a palette color and a goal is measured.
+    #[serde(default)]
+    pub metric: Metric,
+    /// Weights of the 3 channels of `metric`. Uses `Metric::default_channel_weight` when missing.
+    #[serde(default)]
+    pub channel_weight: Option<[f32;3]>,
+    /// The palette color closest to each goal is exposed under the goal's name.
+    #[serde(with = "hex_color_map")]
+    pub goal: IndexMap<String, Color>,
+    /// How much getting each goal right matters, 1 when missing.
+    #[serde(default)]
+    pub weight: IndexMap<String, f32>,
+}
+
+mod hex_color_map {
+    use std::str::FromStr;
+    use indexmap::IndexMap;
+    use serde::{Serialize, Deserialize, Deserializer, Serializer, de};
+
+    use crate::Color;
+
+    pub fn serialize<S: Serializer>(map: &IndexMap<String, Color>, serializer: S) -> Result<S::Ok, S::Error> {
+        map.iter()
+            .map(|(name, color)| (name, format!("#{:x}", color)))
+            .collect::<IndexMap<_, _>>()
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<IndexMap<String, Color>, D::Error> {
+        IndexMap::<String, String>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(name, hex)| match Color::from_str(&hex) {
+                Ok(color) => Ok((name, color)),
+                Err(_) => Err(de::Error::custom(format!("invalid color for {}: {}", name, hex))),
+            })
+            .collect()
+    }
+}
+
+static DEFAULT_CONFIG: &str = include_str!(concat!(env!("OUT_DIR"), "/default_config.json"));
+
+impl Default for Config {
+    fn default() -> Config {
         serde_json::from_str(DEFAULT_CONFIG).unwrap()
     }
+}
+
+impl Config {
     pub fn from_palette(palette: Palette) -> Config {
-        let mut config = Self::default();
-        config.palette = palette;
-        config
+        Config { palette, ..Self::default() }
+    }
+
+    /// `slug`, or else `name` in kebab-case. `None` when `name` has no letter or digit.
+    pub fn slug(&self) -> Option<String> {
+        if self.slug.is_some() {
+            return self.slug.clone();
+        }
+        let name = self.name.as_ref()?;
+        let slug = name.split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>()
+            .join("-");
+        (!slug.is_empty()).then_some(slug)
+    }
+
+    /// Reads a YAML or JSON config file and merges it onto the default config.
+    pub fn from_path(path: &Path) -> Result<Config> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| Error::Config(format!("failed to read {}: {}", path.display(), e)))?;
+        Self::from_str(&content)
     }
 }
 
+impl FromStr for Config {
+    type Err = Error;
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
-pub struct Shades {
-    pub p10: f32,
-    pub p25: f32,
-    pub p50: f32,
-    pub p75: f32,
-    pub p100: f32,
-    pub p125: f32,
+    /// Parses a YAML or JSON config and deep merges it onto the default config.
+    ///
+    /// Mappings are merged key by key, any other value replaces the default one,
+    /// and `null` removes the key from the default config.
+    fn from_str(s: &str) -> Result<Config> {
+        let mut config: Value = serde_json::from_str(DEFAULT_CONFIG).unwrap();
+        // YAML is a superset of JSON, so both are parsed the same way.
+        let user_config: Value = serde_yaml::from_str(s).map_err(|e| Error::Config(e.to_string()))?;
+        if !user_config.is_null() {
+            merge(&mut config, user_config);
+        }
+        serde_json::from_value(config).map_err(|e| Error::Config(e.to_string()))
+    }
+}
+
+fn merge(base: &mut Value, patch: Value) {
+    match (base, patch) {
+        (Value::Object(base), Value::Object(patch)) => {
+            for (key, value) in patch {
+                if value.is_null() {
+                    base.remove(&key);
+                    continue;
+                }
+                match base.get_mut(&key) {
+                    Some(base_value) => merge(base_value, value),
+                    None => { base.insert(key, value); },
+                }
+            }
+        }
+        (base, patch) => *base = patch,
+    }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum ColorNames {
     BuiltIn,
     Reference(Reference),
-    Mapping(HashMap<String, ColorNames>),
+    /// A hex color, e.g. `"#ff8800"`.
+    Literal(Color),
+    /// A computed color, e.g. `mix(c1.p100, red.p100, 0.3)`.
+    Expression(Expression),
+    Mapping(IndexMap<String, ColorNames>),
 }
 
 impl Serialize for ColorNames {
@@ -49,6 +166,8 @@ impl Serialize for ColorNames {
         match self {
             ColorNames::BuiltIn => serializer.serialize_str("BUILT_IN"),
             ColorNames::Reference(s) => serializer.serialize_str(&s.string),
+            ColorNames::Literal(color) => serializer.serialize_str(&format!("#{:x}", color)),
+            ColorNames::Expression(expression) => serializer.serialize_str(&expression.to_string()),
             ColorNames::Mapping(hash) => {
                 use serde::ser::SerializeMap;
                 let mut map = serializer.serialize_map(Some(hash.len()))?;
@@ -67,7 +186,7 @@ impl<'de> Visitor<'de> for ColorNamesVisitor {
     type Value = ColorNames;
 
     fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter.write_str("string or map")
+        formatter.write_str("reference, hex color, expression or map")
     }
 
     fn visit_str<E>(self, s: &str) -> Result<ColorNames, E>
@@ -77,23 +196,38 @@ impl<'de> Visitor<'de> for ColorNamesVisitor {
         if s == "BUILT_IN" {
             return Ok(ColorNames::BuiltIn);
         }
-        if !const_regex::match_regex!(r"[a-z][0-9a-z_]*(\.[a-z][0-9a-z_]*)*", s.as_bytes()) {
-            return Err(E::custom("invalid color name"));
+        if s.starts_with('#') {
+            return Color::from_str(s)
+                .map(ColorNames::Literal)
+                .map_err(|_| E::custom(format!("invalid hex color: {}", s)));
         }
-        Ok(ColorNames::Reference(Reference {string: s.to_string()}))
+        if s.contains('(') {
+            return Expression::from_str(s).map(ColorNames::Expression).map_err(E::custom);
+        }
+        Reference::from_str(s).map(ColorNames::Reference).map_err(E::custom)
     }
 
     fn visit_map<V>(self, mut visitor: V) -> Result<ColorNames, V::Error>
     where
         V: de::MapAccess<'de>,
     {
-        let mut values = HashMap::new();
+        let mut values: IndexMap<String, Value> = IndexMap::new();
 
         while let Some((key, value)) = visitor.next_entry()? {
             values.insert(key, value);
         }
 
-        Ok(ColorNames::Mapping(values))
+        // `{ref: c1.p50, alpha: 0.3}` is the same as `alpha(c1.p50, 0.3)`.
+        if let (2, Some(Value::String(reference)), Some(Value::Number(alpha))) = (values.len(), values.get("ref"), values.get("alpha")) {
+            let color = Expression::from_str(reference).map_err(de::Error::custom)?;
+            let alpha = alpha.as_f64().unwrap_or_default() as f32;
+            return Ok(ColorNames::Expression(Expression::Call(Function::Alpha, vec![color, Expression::Number(alpha)])));
+        }
+
+        values.into_iter()
+            .map(|(key, value)| Ok((key, ColorNames::deserialize(value).map_err(de::Error::custom)?)))
+            .collect::<Result<_, _>>()
+            .map(ColorNames::Mapping)
     }
 }
 
@@ -107,14 +241,87 @@ impl<'de> Deserialize<'de> for ColorNames {
 }
 
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct Reference {
     string: String,
 }
 
+impl fmt::Display for Reference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.string)
+    }
+}
+
+impl FromStr for Reference {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !const_regex::match_regex!(r"[a-z][0-9a-z_]*(\.[a-z][0-9a-z_]*)*", s.as_bytes()) {
+            return Err(format!("invalid color name: {}", s));
+        }
+        Ok(Reference { string: s.to_string() })
+    }
+}
+
 impl Reference {
-    pub fn key_iter<'a>(&'a self) -> std::str::Split<&str>
+    pub fn key_iter(&self) -> std::str::Split<'_, char>
     {
-        self.string.split(".")
+        self.string.split('.')
     }
-}
\ No newline at end of file
+}
+#[test]
+fn from_str_merges_onto_default() {
+    let config = Config::from_str(r#"
+shades:
+  p150: 1.5
+colors:
+  diff:
+    added: green
+    removed: red
+  ansi:
+    black: c1
+"#).unwrap();
+    let default = Config::default();
+    assert_eq!(config.shades.len(), default.shades.len() + 1);
+    assert_eq!(config.palette, default.palette);
+    assert!(matches!(config.colors.get("diff"), Some(ColorNames::Mapping(m)) if m.len() == 2));
+    match config.colors.get("ansi") {
+        Some(ColorNames::Mapping(ansi)) => {
+            assert_eq!(ansi.get("black"), Some(&ColorNames::Reference(Reference { string: "c1".into() })));
+            assert!(ansi.contains_key("red"));
+        }
+        _ => panic!("ansi should be a mapping"),
+    }
+}
+
+#[test]
+fn from_str_removes_null() {
+    let config = Config::from_str(r#"{"shades": {"p125": null}, "colors": {"css": null}}"#).unwrap();
+    assert!(!config.shades.contains_key("p125"));
+    assert!(!config.colors.contains_key("css"));
+    assert_eq!(Config::from_str("").unwrap(), Config::default());
+}
+
+#[test]
+fn from_str_changes_absolute_goal() {
+    let config = Config::from_str(r##"
+absolute:
+  goal:
+    orange: "#ff8000"
+    cyan: null
+"##).unwrap();
+    assert_eq!(config.absolute.goal.get("orange"), Some(&Color::new(0xff, 0x80, 0x00)));
+    assert!(!config.absolute.goal.contains_key("cyan"));
+    assert!(config.absolute.goal.contains_key("red"));
+    assert!(Config::from_str("absolute: {goal: {red: nope}}").is_err());
+}
+
+#[test]
+fn slug_defaults_to_name() {
+    let config = Config::from_str("name: My Cool Theme!").unwrap();
+    assert_eq!(config.slug(), Some("my-cool-theme".to_string()));
+    let config = Config::from_str("{name: My Theme, slug: mine}").unwrap();
+    assert_eq!(config.slug(), Some("mine".to_string()));
+    assert_eq!(Config::default().slug(), None);
+    assert_eq!(Config::from_str("name: '!!!'").unwrap().slug(), None);
+}
@@ -0,0 +1,93 @@
+use ext_palette::{Srgb, RelativeContrast};
+
+use crate::color_science::Rgb;
+
+/// WCAG 2.x contrast ratio between `c1` and `c2`, from 1 to 21. The order doesn't matter.
+pub fn wcag_ratio(c1: &Rgb, c2: &Rgb) -> f32 {
+    let c1: Srgb = c1.into_format();
+    c1.get_contrast_ratio(&c2.into_format())
+}
+
+/// WCAG 2.x level reached by normal text with contrast `ratio`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum WcagLevel {
+    Fail,
+    /// At least 4.5.
+    AA,
+    /// At least 7.
+    AAA,
+}
+
+impl WcagLevel {
+    pub fn of(ratio: f32) -> WcagLevel {
+        if ratio >= 7. {
+            WcagLevel::AAA
+        } else if ratio >= 4.5 {
+            WcagLevel::AA
+        } else {
+            WcagLevel::Fail
+        }
+    }
+}
+
+/// Luminance estimate of APCA, before the soft clamp of near black colors.
+fn apca_y(c: &Rgb) -> f32 {
+    let linear = |x: u8| (x as f32 / 255.).powf(2.4);
+    0.2126729 * linear(c.red) + 0.7151522 * linear(c.green) + 0.0721750 * linear(c.blue)
+}
+
+/// APCA lightness contrast (Lc) of `text` on `bg`, following APCA-W3 0.0.98G-4g.
+///
+/// About -108 to 106. Positive for dark text on light background, negative
+/// otherwise. The order matters.
+pub fn apca_lc(text: &Rgb, bg: &Rgb) -> f32 {
+    let soft_clamp = |y: f32| if y > 0.022 { y } else { y + (0.022 - y).powf(1.414) };
+    let text_y = soft_clamp(apca_y(text));
+    let bg_y = soft_clamp(apca_y(bg));
+    if (bg_y - text_y).abs() < 0.0005 {
+        return 0.;
+    }
+    let lc = if bg_y > text_y {
+        let sapc = (bg_y.powf(0.56) - text_y.powf(0.57)) * 1.14;
+        if sapc < 0.1 { 0. } else { sapc - 0.027 }
+    } else {
+        let sapc = (bg_y.powf(0.65) - text_y.powf(0.62)) * 1.14;
+        if sapc > -0.1 { 0. } else { sapc + 0.027 }
+    };
+    lc * 100.
+}
+
+#[test]
+fn wcag_ratio_works() {
+    assert!((wcag_ratio(&Rgb::new(0, 0, 0), &Rgb::new(255, 255, 255)) - 21.).abs() < 0.01);
+    assert!((wcag_ratio(&Rgb::new(255, 255, 255), &Rgb::new(0x77, 0x77, 0x77)) - 4.48).abs() < 0.01);
+    assert_eq!(WcagLevel::of(4.48), WcagLevel::Fail);
+    assert_eq!(WcagLevel::of(4.5), WcagLevel::AA);
+    assert_eq!(WcagLevel::of(21.), WcagLevel::AAA);
+
+    // Just below the thresholds, while rounding to 2 decimals would reach them.
+    let black = Rgb::new(0, 0, 0);
+    let almost_aa = wcag_ratio(&Rgb::new(39, 134, 0), &black);
+    assert_eq!(format!("{:.2}", almost_aa), "4.50");
+    assert_eq!(WcagLevel::of(almost_aa), WcagLevel::Fail);
+    let almost_aaa = wcag_ratio(&Rgb::new(14, 173, 0), &black);
+    assert_eq!(format!("{:.2}", almost_aaa), "7.00");
+    assert_eq!(WcagLevel::of(almost_aaa), WcagLevel::AA);
+}
+
+#[test]
+fn apca_lc_works() {
+    // Values from the APCA reference implementation.
+    let cases = [
+        ((0x88, 0x88, 0x88), (0xff, 0xff, 0xff), 63.056),
+        ((0xff, 0xff, 0xff), (0x88, 0x88, 0x88), -68.541),
+        ((0x00, 0x00, 0x00), (0xaa, 0xaa, 0xaa), 58.146),
+        ((0xaa, 0xaa, 0xaa), (0x00, 0x00, 0x00), -56.24),
+        ((0x11, 0x22, 0x33), (0xdd, 0xee, 0xff), 91.66),
+        ((0x11, 0x22, 0x33), (0x11, 0x22, 0x33), 0.),
+    ];
+    for (text, bg, expected) in cases {
+        let actual = apca_lc(&Rgb::from(text), &Rgb::from(bg));
+        assert!((actual - expected).abs() < 0.01, "{:?} on {:?}: {} != {}", text, bg, actual, expected);
+    }
+}
@@ -0,0 +1,98 @@
+use ext_palette::{Hsl, IntoColor, Oklch, Srgb};
+
+use crate::{Color, color_science};
+
+/// Parses a color written like in CSS: hex with or without `#` (3, 4, 6 or 8
+/// digits), a named color like `rebeccapurple`, `rgb()`, `hsl()` or `oklch()`.
+///
+/// Alpha is accepted but ignored. Case doesn't matter.
+pub fn parse(s: &str) -> Option<Color> {
+    let s = s.trim().to_ascii_lowercase();
+    if let Some((name, args)) = s.strip_suffix(')').and_then(|s| s.split_once('(')) {
+        return parse_function(name.trim(), args);
+    }
+    let hex = s.strip_prefix('#').unwrap_or(&s);
+    parse_hex(hex).or_else(|| ext_palette::named::from_str(&s))
+}
+
+fn parse_hex(hex: &str) -> Option<Color> {
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).unwrap();
+    let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+    match hex.len() {
+        3 | 4 => Some(Color::new(digit(0) * 17, digit(1) * 17, digit(2) * 17)),
+        6 | 8 => Some(Color::new(byte(0), byte(2), byte(4))),
+        _ => None,
+    }
+}
+
+/// A component of a color function, e.g. `50%`, `0.3` or `120deg`.
+fn parse_number(s: &str, percent_scale: f32) -> Option<f32> {
+    let (number, scale) = match s.strip_suffix('%') {
+        Some(number) => (number, percent_scale / 100.),
+        None => (s.strip_suffix("deg").unwrap_or(s), 1.),
+    };
+    number.parse::<f32>().ok().filter(|x| x.is_finite()).map(|x| x * scale)
+}
+
+fn parse_function(name: &str, args: &str) -> Option<Color> {
+    // Both `rgb(1, 2, 3)` and `rgb(1 2 3 / 0.5)` are valid.
+    let args = args.split_once('/').map_or(args, |(args, _alpha)| args);
+    let args: Vec<&str> = args.split(|c: char| c == ',' || c.is_whitespace()).filter(|x| !x.is_empty()).collect();
+    let args = match args.len() {
+        3 => &args[..],
+        // Legacy `rgba(1, 2, 3, 0.5)`.
+        4 if name.ends_with('a') => &args[..3],
+        _ => return None,
+    };
+    match name {
+        "rgb" | "rgba" => {
+            let mut rgb = [0.; 3];
+            for (x, arg) in rgb.iter_mut().zip(args) {
+                *x = parse_number(arg, 255.)? / 255.;
+            }
+            Some(Srgb::new(rgb[0], rgb[1], rgb[2]).into_format())
+        },
+        "hsl" | "hsla" => {
+            let h = parse_number(args[0], 360.)?;
+            let s = parse_number(args[1], 100.)? / 100.;
+            let l = parse_number(args[2], 100.)? / 100.;
+            let rgb: Srgb = Hsl::new(h, s.clamp(0., 1.), l.clamp(0., 1.)).into_color();
+            Some(rgb.into_format())
+        },
+        "oklch" => {
+            let l = parse_number(args[0], 1.)?;
+            let c = parse_number(args[1], 0.4)?;
+            let h = parse_number(args[2], 360.)?;
+            Some(color_science::gamut_map(Oklch::new(l, c.max(0.), h)).0)
+        },
+        _ => None,
+    }
+}
+
+#[test]
+fn parse_works() {
+    let cases = [
+        ("282936", (0x28, 0x29, 0x36)),
+        ("#E9E9F4", (0xe9, 0xe9, 0xf4)),
+        ("fff", (0xff, 0xff, 0xff)),
+        ("#f80c", (0xff, 0x88, 0x00)),
+        ("#ff880080", (0xff, 0x88, 0x00)),
+        ("RebeccaPurple", (0x66, 0x33, 0x99)),
+        ("rgb(255, 136, 0)", (0xff, 0x88, 0x00)),
+        ("rgba(255 136 0 / 50%)", (0xff, 0x88, 0x00)),
+        ("rgb(100%, 0%, 50%)", (0xff, 0x00, 0x80)),
+        ("hsl(120deg 100% 25%)", (0x00, 0x80, 0x00)),
+        ("hsla(0, 100%, 50%, 0.3)", (0xff, 0x00, 0x00)),
+        ("oklch(100% 0 0)", (0xff, 0xff, 0xff)),
+        ("oklch(0.628 0.2577 29.23)", (0xff, 0x00, 0x00)),
+    ];
+    for (s, expected) in cases {
+        assert_eq!(parse(s), Some(Color::from(expected)), "{}", s);
+    }
+    for s in ["", "#12", "ggg", "notacolor", "rgb(1, 2)", "hsl(a b c)", "lab(50 0 0)"] {
+        assert_eq!(parse(s), None, "{}", s);
+    }
+}
@@ -0,0 +1,78 @@
+use std::fmt;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Errors returned by the library.
+#[derive(Debug)]
+pub enum Error {
+    /// The color at `position` (starting from 1) of a palette code is not a valid color.
+    InvalidColor { position: usize, value: String },
+    /// A palette code doesn't have 9 colors.
+    WrongColorCount(usize),
+    /// A palette code is malformed in another way, e.g. has an unknown slot name.
+    InvalidPaletteCode(String),
+    /// The `@<seed>` of a palette code is not a number.
+    InvalidSeed(String),
+    /// The alias `alias` references `reference`, which doesn't exist.
+    UnknownReference { reference: String, alias: String },
+    /// The alias `alias` references `reference`, which goes into a color instead of a group of colors.
+    ReferenceIntoColor { reference: String, alias: String },
+    /// Aliases reference each other in a loop. Lists the aliases, starting and ending with the same one.
+    CyclicReference(Vec<String>),
+    /// A palette file can't be read or parsed.
+    PaletteFile(String),
+    /// A theme of another format can't be imported.
+    Import(String),
+    /// A config can't be read or parsed.
+    Config(String),
+    /// A template can't be compiled or rendered.
+    Template(mustache::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidColor { position, value } =>
+                write!(f, "color {} in palette is not a valid color: {}", position, value),
+            Error::WrongColorCount(count) =>
+                write!(f, "wrong number of colors in palette: {}, expecting 9", count),
+            Error::InvalidPaletteCode(message) =>
+                write!(f, "invalid palette code: {}", message),
+            Error::InvalidSeed(seed) =>
+                write!(f, "invalid seed: {}", seed),
+            Error::UnknownReference { reference, alias } =>
+                write!(f, "unknown reference `{}` at alias `{}`", reference, alias),
+            Error::ReferenceIntoColor { reference, alias } =>
+                write!(f, "reference `{}` at alias `{}` goes into a color", reference, alias),
+            Error::CyclicReference(cycle) =>
+                write!(f, "cyclic reference: {}", cycle.join(" -> ")),
+            Error::PaletteFile(message) =>
+                write!(f, "invalid palette file: {}", message),
+            Error::Import(message) =>
+                write!(f, "import failed: {}", message),
+            Error::Config(message) =>
+                write!(f, "invalid config: {}", message),
+            // `Display` of `mustache::Error` only prints a deprecation message.
+            Error::Template(mustache::Error::Parser(error)) =>
+                write!(f, "template error: {}", error),
+            #[allow(deprecated)]
+            Error::Template(error) =>
+                write!(f, "template error: {}", std::error::Error::description(error)),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Template(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<mustache::Error> for Error {
+    fn from(error: mustache::Error) -> Self {
+        Error::Template(error)
+    }
+}
@@ -0,0 +1,214 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::Color;
+use crate::config::Reference;
+
+/// Functions usable in the `colors` section of config.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Function {
+    /// `mix(a, b, w)`: moves `a` toward `b` by `w`, see `color_science::mix`.
+    Mix,
+    /// `lighten(c, amount)`: increases Lab lightness by `amount` of its whole range.
+    Lighten,
+    /// `darken(c, amount)`: decreases Lab lightness by `amount` of its whole range.
+    Darken,
+    /// `alpha(c, a)`: `c` with opacity `a`, from 0 to 1.
+    Alpha,
+    /// `contrast_on(c)`: the background or foreground, whichever contrasts more with `c`.
+    ContrastOn,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Param {
+    Color,
+    Number,
+}
+
+impl Function {
+    fn name(&self) -> &'static str {
+        match self {
+            Function::Mix => "mix",
+            Function::Lighten => "lighten",
+            Function::Darken => "darken",
+            Function::Alpha => "alpha",
+            Function::ContrastOn => "contrast_on",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Function> {
+        [Function::Mix, Function::Lighten, Function::Darken, Function::Alpha, Function::ContrastOn]
+            .into_iter()
+            .find(|f| f.name() == name)
+    }
+
+    fn params(&self) -> &'static [Param] {
+        match self {
+            Function::Mix => &[Param::Color, Param::Color, Param::Number],
+            Function::Lighten | Function::Darken | Function::Alpha => &[Param::Color, Param::Number],
+            Function::ContrastOn => &[Param::Color],
+        }
+    }
+}
+
+/// A color computed from other colors, e.g. `mix(c1.p100, red.p100, 0.3)`.
+///
+/// Arguments referencing a group of colors (like `c2`) apply the function to
+/// each color of the group.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Expression {
+    Reference(Reference),
+    Literal(Color),
+    /// `10%` is the same as `0.1`.
+    Number(f32),
+    Call(Function, Vec<Expression>),
+}
+
+impl Expression {
+    fn param(&self) -> Param {
+        match self {
+            Expression::Number(_) => Param::Number,
+            _ => Param::Color,
+        }
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expression::Reference(reference) => write!(f, "{}", reference),
+            Expression::Literal(color) => write!(f, "#{:x}", color),
+            Expression::Number(number) => write!(f, "{}", number),
+            Expression::Call(function, args) => {
+                write!(f, "{}(", function.name())?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                f.write_str(")")
+            },
+        }
+    }
+}
+
+impl FromStr for Expression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { s, pos: 0 };
+        let expression = parser.expression()?;
+        parser.skip_whitespace();
+        if parser.pos != s.len() {
+            return Err(format!("unexpected `{}` in `{}`", &s[parser.pos..], s));
+        }
+        if expression.param() != Param::Color {
+            return Err(format!("`{}` is not a color", s));
+        }
+        Ok(expression)
+    }
+}
+
+struct Parser<'a> {
+    s: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.s[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.pos = self.s.len() - self.rest().trim_start().len();
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+        let start = self.pos;
+        let len = self.rest().find(|c| !f(c)).unwrap_or(self.rest().len());
+        self.pos += len;
+        &self.s[start..self.pos]
+    }
+
+    fn expression(&mut self) -> Result<Expression, String> {
+        self.skip_whitespace();
+        match self.rest().chars().next() {
+            Some('#') => {
+                let hex = self.take_while(|c| c == '#' || c.is_ascii_hexdigit());
+                Color::from_str(hex)
+                    .map(Expression::Literal)
+                    .map_err(|_| format!("invalid hex color `{}`", hex))
+            },
+            Some(c) if c.is_ascii_digit() || c == '.' || c == '-' => {
+                let negative = self.eat('-');
+                let number = self.take_while(|c| c.is_ascii_digit() || c == '.');
+                let number = f32::from_str(number).map_err(|_| format!("invalid number `{}`", number))?;
+                let number = if negative { -number } else { number };
+                Ok(Expression::Number(if self.eat('%') { number / 100. } else { number }))
+            },
+            Some(c) if c.is_ascii_lowercase() => {
+                let name = self.take_while(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.');
+                if !self.eat('(') {
+                    return Reference::from_str(name).map(Expression::Reference);
+                }
+                let function = Function::from_name(name).ok_or_else(|| format!("unknown function `{}`", name))?;
+                let mut args = Vec::new();
+                if !self.eat(')') {
+                    loop {
+                        args.push(self.expression()?);
+                        if self.eat(')') {
+                            break;
+                        }
+                        if !self.eat(',') {
+                            return Err(format!("expecting `,` or `)` in `{}`", self.s));
+                        }
+                    }
+                }
+                let params: Vec<Param> = args.iter().map(Expression::param).collect();
+                if params != function.params() {
+                    let expected: Vec<&str> = function.params().iter().map(|p| match p {
+                        Param::Color => "color",
+                        Param::Number => "number",
+                    }).collect();
+                    return Err(format!("`{}` expects ({})", name, expected.join(", ")));
+                }
+                Ok(Expression::Call(function, args))
+            },
+            _ => Err(format!("expecting a color or number in `{}`", self.s)),
+        }
+    }
+}
+
+#[test]
+fn from_str_works() {
+    let expression = Expression::from_str("mix(c1.p100, lighten(#FF8800, 10%), 0.3)").unwrap();
+    assert_eq!(expression.to_string(), "mix(c1.p100, lighten(#ff8800, 0.1), 0.3)");
+    assert_eq!(Expression::from_str(&expression.to_string()), Ok(expression));
+    assert_eq!(Expression::from_str(" contrast_on( c1 ) ").unwrap().to_string(), "contrast_on(c1)");
+    assert_eq!(Expression::from_str("lighten(c1, -10%)").unwrap().to_string(), "lighten(c1, -0.1)");
+}
+
+#[test]
+fn from_str_errors() {
+    assert!(Expression::from_str("0.3").is_err());
+    assert!(Expression::from_str("nope(c1)").is_err());
+    assert!(Expression::from_str("mix(c1, c2)").is_err());
+    assert!(Expression::from_str("alpha(0.4, c2)").is_err());
+    assert!(Expression::from_str("alpha(c2, 0.4").is_err());
+    assert!(Expression::from_str("alpha(C2, 0.4)").is_err());
+    assert!(Expression::from_str("darken(#ff88zz, 0.4)").is_err());
+    assert!(Expression::from_str("darken(c1, -)").is_err());
+    assert!(Expression::from_str("darken(c1, --0.1)").is_err());
+    assert!(Expression::from_str("-c1").is_err());
+}
@@ -1,17 +1,72 @@
 use crate::palette::Palette;
-use ext_palette::{IntoColor, Lab, rgb::channels::Argb, Srgb};
+use ext_palette::{IntoColor, Lab, rgb::channels::Argb, Srgb, convert::IntoColorUnclamped};
 use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+use serde::{Serialize, Deserialize};
 use std::{ops::RangeInclusive, f32::consts::PI};
-// use itertools::Itertools;
 type Color = ext_palette::Lab;
 
-use crate::{palette::PaletteOption};
+use crate::{palette::PaletteOption, color_science, contrast};
 
-#[derive(Debug)]
-struct Config {
-    is_dark: Option<bool>,
-    hue_l: Option<RangeInclusive<f32>>,
-    hue_c: Option<RangeInclusive<f32>>,
+/// Minimum WCAG contrast ratios against the background, see `generate_constrained`.
+#[derive(Debug, Default, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContrastConstraints {
+    /// For the foreground.
+    pub foreground: Option<f32>,
+    /// For each of `c1` to `c7`.
+    pub hue: Option<f32>,
+}
+
+/// A color of a generated palette below its minimum contrast against the background.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct ContrastViolation {
+    /// Index in the palette, 1 for the foreground, 2 to 8 for `c1` to `c7`.
+    pub index: usize,
+    pub contrast: f32,
+    pub min_contrast: f32,
+    /// Whether the color was given in the palette option instead of generated.
+    pub fixed: bool,
+}
+
+impl ContrastViolation {
+    pub fn name(&self) -> String {
+        match self.index {
+            1 => "foreground".to_string(),
+            i => format!("c{}", i - 1),
+        }
+    }
+}
+
+impl std::fmt::Display for ContrastViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} has contrast {:.2} against background, below {}", self.name(), self.contrast, self.min_contrast)?;
+        if self.fixed {
+            f.write_str(" (given color)")?;
+        } else {
+            f.write_str(" (no lightness reaches it)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Options of `generate_with_options`. `None` lets the generator decide,
+/// mostly from the colors given in the palette option.
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GeneratorOptions {
+    /// Dark background and light foreground. Random when no color is given.
+    pub is_dark: Option<bool>,
+    /// Lab lightness of generated hues, from 0 to 100.
+    pub hue_l: Option<RangeInclusive<f32>>,
+    /// Lab chroma of generated hues, i.e. the distance from the gray between
+    /// background and foreground. About 0 to 130, low values are muted.
+    pub hue_c: Option<RangeInclusive<f32>>,
+    /// Lab hue angle in degrees of the first generated hue, when no hue is given.
+    pub hue_start: Option<f32>,
+    /// Degrees between consecutive generated hues when at most one hue is given. `360/7` by default.
+    pub hue_spacing: Option<f32>,
+    pub min_contrast: ContrastConstraints,
 }
 
 // struct Generator {
@@ -57,42 +112,100 @@ pub fn get_average_hue(palette_option: &PaletteOption) -> Option<Color> {
 }
 
 
+/// Fills the unspecified colors of `palette_option`.
+///
+/// Uses the seed of `palette_option` when it has one, otherwise the result is random.
 pub fn generate(palette_option: &PaletteOption) -> Palette {
+    match palette_option.seed {
+        Some(seed) => generate_seeded(palette_option, seed),
+        None => generate_with_rng(palette_option, &mut rand::thread_rng()),
+    }
+}
+
+/// Same `palette_option` and `seed` always generate the same palette, also across
+/// releases of `rand`.
+pub fn generate_seeded(palette_option: &PaletteOption, seed: u64) -> Palette {
+    generate_with_rng(palette_option, &mut ChaCha8Rng::seed_from_u64(seed))
+}
+
+pub fn generate_with_rng(palette_option: &PaletteOption, rng: &mut impl Rng) -> Palette {
+    generate_with_options(palette_option, &GeneratorOptions::default(), rng).0
+}
+
+/// Same as `generate_with_rng`, but adjusts the lightness of generated colors
+/// until they meet `constraints`.
+///
+/// Also returns the colors still below their minimum contrast, mostly colors
+/// given in `palette_option`.
+pub fn generate_constrained(palette_option: &PaletteOption, constraints: &ContrastConstraints, rng: &mut impl Rng) -> (Palette, Vec<ContrastViolation>) {
+    let options = GeneratorOptions { min_contrast: *constraints, ..GeneratorOptions::default() };
+    generate_with_options(palette_option, &options, rng)
+}
+
+/// Same as `generate_constrained`, but follows all of `options`. The
+/// constraints are `options.min_contrast`.
+pub fn generate_with_options(palette_option: &PaletteOption, options: &GeneratorOptions, rng: &mut impl Rng) -> (Palette, Vec<ContrastViolation>) {
+    let constraints = &options.min_contrast;
     if palette_option.colors.iter().all(|x| x.is_some()) {
-        return Palette { colors: palette_option.colors.map(|x| x.unwrap()) };
+        let palette = Palette { colors: palette_option.colors.map(|x| x.unwrap()) };
+        let violations = check_constraints(&palette, constraints, &[true; 9]);
+        return (palette, violations);
     }
-    let mut colors = palette_option.colors.clone().map(|c| c.map(to_lab));
-
-    // let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
-    let mut rng = rand::thread_rng();
-    let mut config = Config {
-        is_dark: None,
-        // is_dark: Some(false),
-        hue_l: None,
-        hue_c: None,
-        // hue_c: Some(50f32..=50f32),
-    };
+    let fixed = palette_option.colors.map(|c| c.is_some());
+    let mut colors = palette_option.colors.map(|c| c.map(to_lab));
+
+    let mut options = options.clone();
 
     let average_hue = get_average_hue(palette_option);
-    config.is_dark.get_or_insert(match (colors, average_hue) {
+    options.is_dark.get_or_insert(match (colors, average_hue) {
         ([Some(c), ..], _) => is_dark(&c),
         ([None, Some(c), ..], _) => !is_dark(&c),
         ([None, None, ..], Some(c)) => !is_dark(&c),
-        ([None, None, ..], None) => rand::random::<bool>(),
+        ([None, None, ..], None) => rng.gen::<bool>(),
     });
-    generate_bg(&mut rng, &mut colors, &config);
-    generate_fg(&mut rng, &mut colors, &config);
+    generate_bg(rng, &mut colors, &options);
+    generate_fg(rng, &mut colors, &options);
+    if let (false, Some(min_contrast)) = (fixed[1], constraints.foreground) {
+        colors[1] = Some(adjust_lightness(colors[1].unwrap(), &colors[0].unwrap(), min_contrast));
+    }
+
+    generate_hue(rng, &mut colors, &mut options);
+    if let Some(min_contrast) = constraints.hue {
+        let bg = colors[0].unwrap();
+        for (c, _) in colors[2..].iter_mut().zip(&fixed[2..]).filter(|(_, fixed)| !**fixed) {
+            *c = Some(adjust_lightness(c.unwrap(), &bg, min_contrast));
+        }
+    }
+    let palette = Palette { colors: colors.map(|x| from_lab(x.unwrap())) };
+    let violations = check_constraints(&palette, constraints, &fixed);
+    (palette, violations)
+}
 
-    generate_hue(&mut rng, &mut colors, &mut config);
-    // println!("{:?}", config);
-    Palette { colors: colors.map(|x| from_lab(x.unwrap())) }
+fn check_constraints(palette: &Palette, constraints: &ContrastConstraints, fixed: &[bool; 9]) -> Vec<ContrastViolation> {
+    let bg = palette.colors[0];
+    (1..9).filter_map(|index| {
+        let min_contrast = if index == 1 { constraints.foreground } else { constraints.hue }?;
+        let contrast = contrast::wcag_ratio(&palette.colors[index], &bg);
+        (contrast < min_contrast).then_some(ContrastViolation { index, contrast, min_contrast, fixed: fixed[index] })
+    }).collect()
+}
+
+/// Moves the lightness of `c` toward black or white, whichever contrasts more
+/// with `bg`, until `c` contrasts at least `min_contrast` with `bg`.
+fn adjust_lightness(mut c: Color, bg: &Color, min_contrast: f32) -> Color {
+    let bg = from_lab(*bg);
+    let step = if contrast::wcag_ratio(&crate::Color::new(255, 255, 255), &bg) >= contrast::wcag_ratio(&crate::Color::new(0, 0, 0), &bg) { 1. } else { -1. };
+    while contrast::wcag_ratio(&from_lab(c), &bg) < min_contrast && (0. ..=100.).contains(&(c.l + step)) {
+        c.l += step;
+    }
+    c
 }
 
 fn get_dark_color(rng: &mut impl rand::Rng) -> Color {
     let lab_0: Color = Srgb::from_u32::<Argb>(0xff_10009cu32).into_format().into_color();
     let lab_x: Color = Srgb::from_u32::<Argb>(0xff_003000u32).into_format().into_color();
     let lab_y: Color = Srgb::from_u32::<Argb>(0xff_5a0000u32).into_format().into_color();
-    let mut rand_l: f32 = rng.gen();
+    let rand_l: f32 = rng.gen();
     let mut rand_x: f32 = rng.gen();
     let mut rand_y: f32 = rng.gen();
     if rand_x + rand_y > 1. {
@@ -125,7 +238,7 @@ fn get_light_color(rng: &mut impl rand::Rng) -> Color {
     let lab_0: Color = Srgb::from_u32::<Argb>(0xff_00e2ffu32).into_format().into_color();
     let lab_x: Color = Srgb::from_u32::<Argb>(0xff_00ef00u32).into_format().into_color();
     let lab_y: Color = Srgb::from_u32::<Argb>(0xff_ffadffu32).into_format().into_color();
-    let mut rand_l: f32 = rng.gen();
+    let rand_l: f32 = rng.gen();
     let mut rand_x: f32 = rng.gen();
     let mut rand_y: f32 = rng.gen();
 
@@ -156,18 +269,18 @@ fn is_dark(c: &Lab) -> bool {
     c.l < 50.0
 }
 
-fn generate_bg(rng: &mut impl rand::Rng, colors: &mut [Option<Color>;9], config: &Config) {
+fn generate_bg(rng: &mut impl rand::Rng, colors: &mut [Option<Color>;9], options: &GeneratorOptions) {
     if colors[0].is_some() { return; }
-    colors[0] = Some(if config.is_dark.unwrap() {
+    colors[0] = Some(if options.is_dark.unwrap() {
         get_dark_color(rng)
     } else {
         get_light_color(rng)
     });
 }
 
-fn generate_fg(rng: &mut impl rand::Rng, colors: &mut [Option<Color>;9], config: &Config) {
+fn generate_fg(rng: &mut impl rand::Rng, colors: &mut [Option<Color>;9], options: &GeneratorOptions) {
     if colors[1].is_some() { return; }
-    colors[1] = Some(if config.is_dark.unwrap() {
+    colors[1] = Some(if options.is_dark.unwrap() {
         get_light_color(rng)
     } else {
         get_dark_color(rng)
@@ -179,7 +292,7 @@ fn to_lab(c: crate::Color) -> Color {
 }
 
 fn from_lab(lab: Color) -> crate::Color {
-    IntoColor::<Srgb>::into_color(lab).into_format()
+    color_science::gamut_map(lab.into_color_unclamped()).0
 }
 
 pub fn length_and_angle(lab: &Color, base: &Color) -> (f32, f32) {
@@ -202,7 +315,7 @@ fn interpolate(bg: &Color, fg: &Color, l: f32) -> Color {
     center
 }
 
-fn generate_hue(rng: &mut impl rand::Rng, colors: &mut [Option<Color>;9], config: &mut Config) {
+fn generate_hue(rng: &mut impl rand::Rng, colors: &mut [Option<Color>;9], options: &mut GeneratorOptions) {
     let bg: Color = colors[0].unwrap();
     let fg: Color = colors[1].unwrap();
 
@@ -227,30 +340,30 @@ fn generate_hue(rng: &mut impl rand::Rng, colors: &mut [Option<Color>;9], config
             count += 1;
         }
         if count == 0 {
-            max_l = if config.is_dark.unwrap() {
+            max_l = if options.is_dark.unwrap() {
                 fg.l * 0.8
             } else {
                 100. * (1. - 0.8) + fg.l*0.8
             };
             min_l = max_l;
-            max_c = if config.is_dark.unwrap() {
+            max_c = if options.is_dark.unwrap() {
                 rng.gen_range(10f32..50f32)
             } else {
                 rng.gen_range(20f32..50f32)
             };
             min_c = max_c;
         }
-        config.hue_l.get_or_insert(min_l..=max_l);
-        config.hue_c.get_or_insert(min_c..=max_c);
+        options.hue_l.get_or_insert(min_l..=max_l);
+        options.hue_c.get_or_insert(min_c..=max_c);
     }
-    let mut new_angles = get_new_angles(rng, &angles);
+    let mut new_angles = get_new_angles(rng, &angles, options);
     // println!("{:?}...{:?}", angles, new_angles);
     for c in colors {
         if c.is_some() { continue; }
 
         let angle = new_angles.pop().unwrap();
-        let l = rng.gen_range(config.hue_l.clone().unwrap());
-        let hue_distance = rng.gen_range(config.hue_c.clone().unwrap());
+        let l = rng.gen_range(ordered(options.hue_l.as_ref().unwrap()));
+        let hue_distance = rng.gen_range(ordered(options.hue_c.as_ref().unwrap()));
         // let l = 0.;
         // let hue_distance = 0.;
         let mut lab = interpolate(&bg, &fg, l);
@@ -261,6 +374,10 @@ fn generate_hue(rng: &mut impl rand::Rng, colors: &mut [Option<Color>;9], config
     }
 }
 
+fn ordered(range: &RangeInclusive<f32>) -> RangeInclusive<f32> {
+    range.start().min(*range.end())..=range.start().max(*range.end())
+}
+
 fn angle_to_distance(a: f32) -> f32 {
     a
 }
@@ -269,18 +386,22 @@ fn distance_to_angle(a: f32) -> f32 {
     a
 }
 
-fn get_new_angles(rng: &mut impl Rng, angles: &Vec<f32>) -> Vec<f32> {
+fn get_new_angles(rng: &mut impl Rng, angles: &[f32], options: &GeneratorOptions) -> Vec<f32> {
     let remaining = 7 - angles.len();
     let mut rtn = Vec::<f32>::with_capacity(remaining);
     if remaining == 0 { return rtn; }
     let mut valid_distances: Vec<f32> = angles.iter().filter(|x| x.is_finite()).map(|x| angle_to_distance(*x)).collect();
     let module = PI * 2.;
     valid_distances.sort_by(|a,b| a.partial_cmp(b).unwrap());
-    if valid_distances.len() == 0 {
-        let start = rng.gen_range(0f32..module);
+    let spacing = options.hue_spacing.map_or(module / 7., f32::to_radians);
+    if valid_distances.is_empty() {
+        let start = match options.hue_start {
+            Some(degrees) => degrees.to_radians().rem_euclid(module),
+            None => rng.gen_range(0f32..module),
+        };
         rtn.push(distance_to_angle(start));
         for i in 1..7 {
-            rtn.push(distance_to_angle((start + i as f32 * module / 7 as f32) % module));
+            rtn.push(distance_to_angle((start + i as f32 * spacing).rem_euclid(module)));
         }
         rtn.shuffle(rng);
         return rtn;
@@ -288,7 +409,7 @@ fn get_new_angles(rng: &mut impl Rng, angles: &Vec<f32>) -> Vec<f32> {
     if valid_distances.len() == 1 {
         let start = valid_distances[0];
         for i in 1..7 {
-            rtn.push(distance_to_angle((start + i as f32 * module / 7 as f32) % module));
+            rtn.push(distance_to_angle((start + i as f32 * spacing).rem_euclid(module)));
         }
         rtn.shuffle(rng);
         return rtn;
@@ -318,3 +439,115 @@ fn get_new_angles(rng: &mut impl Rng, angles: &Vec<f32>) -> Vec<f32> {
     rtn.shuffle(rng);
     rtn
 }
+
+/// Palette of the opposite polarity: a dark palette becomes light and vice versa.
+///
+/// Background and foreground swap their lightness but keep their tint. Each
+/// hue keeps its angle and chroma around the gray between background and
+/// foreground, and its relative lightness between them, then is moved away
+/// from the new background until it contrasts at least as much as before.
+///
+/// Chroma is kept absolute, not scaled: the lightness spread between
+/// background and foreground is the same in both palettes. Colors out of the
+/// sRGB gamut at their new lightness lose chroma in `gamut_map`.
+pub fn counterpart(palette: &Palette) -> Palette {
+    let colors = palette.colors.map(to_lab);
+    let (bg, fg) = (colors[0], colors[1]);
+    let new_bg = Color::new(fg.l, bg.a, bg.b);
+    let new_fg = Color::new(bg.l, fg.a, fg.b);
+    let mut new_colors = [new_bg, new_fg, new_bg, new_bg, new_bg, new_bg, new_bg, new_bg, new_bg];
+    for (new_c, c) in new_colors[2..].iter_mut().zip(&colors[2..]) {
+        let (chroma, angle) = length_and_angle(c, &interpolate(&bg, &fg, c.l));
+        let t = if (fg.l - bg.l).abs() < f32::EPSILON { 0.5 } else { (c.l - bg.l) / (fg.l - bg.l) };
+        let mut lab = interpolate(&new_bg, &new_fg, new_bg.l + t * (new_fg.l - new_bg.l));
+        lab.a += angle.cos() * chroma;
+        lab.b += angle.sin() * chroma;
+        let min_contrast = contrast::wcag_ratio(&from_lab(*c), &from_lab(bg));
+        *new_c = adjust_lightness(lab, &new_bg, min_contrast);
+    }
+    Palette { colors: new_colors.map(from_lab) }
+}
+
+#[test]
+fn generate_seeded_is_deterministic() {
+    use std::str::FromStr;
+    let palette_option = PaletteOption::from_str("_-ffffff-?").unwrap();
+    let palette = generate_seeded(&palette_option, 42);
+    assert_eq!(palette, generate_seeded(&palette_option, 42));
+    assert_eq!(palette.to_string(), "0d0e0a-ffffff-ffafb1-aed279-feabf4-40ddbd-a1c8ff-f9bb70-00daff");
+}
+
+#[test]
+fn generate_uses_seed_of_palette_option() {
+    use std::str::FromStr;
+    let palette_option = PaletteOption::from_str("?@42").unwrap();
+    assert_eq!(generate(&palette_option), generate_seeded(&palette_option, 42));
+}
+
+#[test]
+fn generate_constrained_meets_contrast() {
+    use std::str::FromStr;
+    let constraints = ContrastConstraints { foreground: Some(7.), hue: Some(4.5) };
+    for seed in 0..20 {
+        let palette_option = PaletteOption::from_str("?").unwrap();
+        let (palette, violations) = generate_constrained(&palette_option, &constraints, &mut ChaCha8Rng::seed_from_u64(seed));
+        assert!(violations.is_empty(), "{}: {:?}", palette, violations);
+    }
+
+    // c1 is given and too close to the background.
+    let palette_option = PaletteOption::from_str("000000-_-111111-?").unwrap();
+    let (palette, violations) = generate_constrained(&palette_option, &constraints, &mut ChaCha8Rng::seed_from_u64(0));
+    assert_eq!(violations.len(), 1, "{}: {:?}", palette, violations);
+    assert_eq!(violations[0].name(), "c1");
+    assert!(violations[0].fixed);
+}
+
+#[test]
+fn generate_with_options_follows_options() {
+    use std::str::FromStr;
+    let options = GeneratorOptions {
+        is_dark: Some(false),
+        hue_l: Some(80. ..=85.),
+        hue_c: Some(15. ..=20.),
+        hue_start: Some(30.),
+        hue_spacing: Some(10.),
+        ..Default::default()
+    };
+    let palette_option = PaletteOption::from_str("?").unwrap();
+    let (palette, _) = generate_with_options(&palette_option, &options, &mut ChaCha8Rng::seed_from_u64(0));
+    let bg = to_lab(palette.colors[0]);
+    let fg = to_lab(palette.colors[1]);
+    assert!(bg.l > fg.l);
+    let mut angles = Vec::new();
+    for c in &palette.colors[2..] {
+        let lab = to_lab(*c);
+        assert!((79. ..=86.).contains(&lab.l), "{}", palette);
+        let (chroma, angle) = length_and_angle(&lab, &interpolate(&bg, &fg, lab.l));
+        assert!((14. ..=21.).contains(&chroma), "{}", palette);
+        angles.push(angle.to_degrees().rem_euclid(360.));
+    }
+    angles.sort_by(|a, b| a.total_cmp(b));
+    for (i, angle) in angles.iter().enumerate() {
+        assert!((angle - (30. + 10. * i as f32)).abs() < 3., "{:?}", angles);
+    }
+}
+
+#[test]
+fn counterpart_inverts_polarity() {
+    use std::str::FromStr;
+    let palette = Palette::from_str("282936-E9E9F4-FF5555-FFB86C-F1FA8C-50FA7B-8BE9FD-BD93F9-FF79C6").unwrap();
+    let light = counterpart(&palette);
+    let (bg, fg) = (to_lab(light.colors[0]), to_lab(light.colors[1]));
+    assert!(bg.l > fg.l, "{}", light);
+    let (old_bg, old_fg) = (to_lab(palette.colors[0]), to_lab(palette.colors[1]));
+    for (c, old_c) in light.colors[2..].iter().zip(&palette.colors[2..]) {
+        let contrast = contrast::wcag_ratio(c, &light.colors[0]);
+        assert!(contrast >= contrast::wcag_ratio(old_c, &palette.colors[0]) - 0.05, "{}", light);
+        let (c, old_c) = (to_lab(*c), to_lab(*old_c));
+        let (_, angle) = length_and_angle(&c, &interpolate(&bg, &fg, c.l));
+        let (_, old_angle) = length_and_angle(&old_c, &interpolate(&old_bg, &old_fg, old_c.l));
+        let difference = (angle - old_angle).rem_euclid(2. * PI);
+        assert!(difference.min(2. * PI - difference) < 0.3, "{}", light);
+    }
+    assert!(to_lab(counterpart(&light).colors[0]).l < 50.);
+}
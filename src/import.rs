@@ -0,0 +1,911 @@
+use ext_palette::{IntoColor, Lab};
+use serde_yaml::Value;
+
+use crate::{Color, css_color, color_science, palette::Palette, error::{Error, Result}};
+
+/// CIEDE2000 distance under which two candidates of `pick_hues` count as the same color.
+const SAME_COLOR_DISTANCE: f32 = 5.;
+
+/// Picks 7 hues among `candidates`, ordered by hue angle from red to magenta.
+///
+/// Candidates close to an earlier one are dropped. Starting from the first
+/// candidate, the one with the largest CIEDE2000 distance to the hues already
+/// picked is added until there are 7, ignoring lightness so that a bright
+/// variant only comes after all other hues. Terminal themes have 6 hues, so
+/// one of them is picked twice.
+fn pick_hues(candidates: &[Color]) -> Result<[Color; 7]> {
+    let lab = |c: &Color| -> Lab { c.into_format().into_color() };
+    let distance = |a: &Color, b: &Color, weight| color_science::ciede2000(&lab(a), &lab(b), weight);
+    let mut distinct: Vec<Color> = Vec::new();
+    for c in candidates {
+        if distinct.iter().all(|d| distance(c, d, [1., 1., 1.]) >= SAME_COLOR_DISTANCE) {
+            distinct.push(*c);
+        }
+    }
+    if distinct.len() < 7 {
+        return Err(Error::Import(format!("only {} distinct hues found, expecting at least 7", distinct.len())));
+    }
+    let mut picked = vec![distinct[0]];
+    while picked.len() < 7 {
+        let farthest = distinct.iter()
+            .filter(|c| !picked.contains(c))
+            .map(|c| (c, picked.iter().map(|p| distance(c, p, [0., 1., 1.])).fold(f32::MAX, f32::min)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap().0;
+        picked.push(*farthest);
+    }
+    // Starting at 10 degrees puts pinks after magenta rather than before red.
+    let hue = |c: &Color| {
+        let lab = lab(c);
+        (lab.b.atan2(lab.a).to_degrees() - 10.).rem_euclid(360.)
+    };
+    picked.sort_by(|a, b| hue(a).total_cmp(&hue(b)));
+    Ok([(); 7].map(|_| picked.remove(0)))
+}
+
+/// Converts a base16 or base24 scheme to a palette: background is `base00`,
+/// foreground is `base05` and the hues are picked among `base08` to `base0F`.
+///
+/// Both the original format (colors at the top level) and the tinted-theming
+/// format (colors under `palette`) are accepted. Extra base24 colors are ignored.
+pub fn import_base16(s: &str) -> Result<Palette> {
+    let scheme: Value = serde_yaml::from_str(s).map_err(|e| Error::Import(e.to_string()))?;
+    let colors = scheme.get("palette").unwrap_or(&scheme);
+    let color = |key: &str| -> Result<Color> {
+        let value = colors.get(key)
+            .or_else(|| colors.get(key.to_ascii_lowercase()))
+            .ok_or_else(|| Error::Import(format!("missing `{}`", key)))?;
+        // Unquoted all-digit codes like `181818` are read as YAML integers.
+        let hex = match value {
+            Value::String(s) => Some(s.clone()),
+            Value::Number(n) => n.as_u64().map(|n| format!("{:06}", n)),
+            _ => None,
+        };
+        hex.as_deref()
+            .and_then(css_color::parse)
+            .ok_or_else(|| Error::Import(format!("invalid color for `{}`: {}", key, serde_yaml::to_string(value).unwrap_or_default().trim())))
+    };
+    let hues = ["base08", "base09", "base0A", "base0B", "base0C", "base0D", "base0E", "base0F"]
+        .into_iter()
+        .map(color)
+        .collect::<Result<Vec<_>>>()?;
+    let [c1, c2, c3, c4, c5, c6, c7] = pick_hues(&hues)?;
+    Ok(Palette { colors: [color("base00")?, color("base05")?, c1, c2, c3, c4, c5, c6, c7] })
+}
+
+/// Names of the 8 ANSI colors, the bright ones follow in the same order.
+const ANSI_NAMES: [&str; 8] = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+
+/// Parses a color of a terminal theme, also accepting `0xrrggbb` and X11 `rgb:r/g/b`.
+fn parse_color(s: &str) -> Option<Color> {
+    let s = s.trim().trim_matches(|c| c == '"' || c == '\'');
+    if let Some(hex) = s.strip_prefix("0x") {
+        return css_color::parse(hex);
+    }
+    if let Some(rgb) = s.strip_prefix("rgb:") {
+        let components = rgb.split('/')
+            .map(|x| {
+                if !(1..=4).contains(&x.len()) {
+                    return None;
+                }
+                let max = 16u32.pow(x.len() as u32) - 1;
+                let value = u32::from_str_radix(x, 16).ok()?;
+                Some((value * 255 + max / 2) / max)
+            })
+            .collect::<Option<Vec<u32>>>()?;
+        return match components[..] {
+            [r, g, b] => Some(Color::new(r as u8, g as u8, b as u8)),
+            _ => None,
+        };
+    }
+    css_color::parse(s)
+}
+
+/// Colors of a terminal theme. `ansi` are the 16 ANSI colors.
+#[derive(Default)]
+struct TerminalColors {
+    background: Option<Color>,
+    foreground: Option<Color>,
+    ansi: [Option<Color>; 16],
+}
+
+impl TerminalColors {
+    /// The color named `key`: `background`, `foreground` or the index of an ANSI color.
+    fn slot(&mut self, key: &str) -> Option<&mut Option<Color>> {
+        match key {
+            "background" => Some(&mut self.background),
+            "foreground" => Some(&mut self.foreground),
+            key => match key.parse::<usize>() {
+                Ok(i) if i < 16 => Some(&mut self.ansi[i]),
+                _ => None,
+            },
+        }
+    }
+
+    /// Parses `value` into the color named `key`, other keys are ignored.
+    fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        if let Some(slot) = self.slot(key) {
+            *slot = Some(parse_color(value).ok_or_else(|| Error::Import(format!("invalid color for `{}`: {}", key, value)))?);
+        }
+        Ok(())
+    }
+
+    /// Sets the color named `key`, other keys are ignored.
+    fn set_color(&mut self, key: &str, color: Color) {
+        if let Some(slot) = self.slot(key) {
+            *slot = Some(color);
+        }
+    }
+
+    /// Picks the hues among the ANSI colors, skipping the black and white ones.
+    fn to_palette(&self) -> Result<Palette> {
+        let background = self.background.ok_or_else(|| Error::Import("missing background".to_string()))?;
+        let foreground = self.foreground.ok_or_else(|| Error::Import("missing foreground".to_string()))?;
+        let hues: Vec<Color> = (1..7).chain(9..15).filter_map(|i| self.ansi[i]).collect();
+        let [c1, c2, c3, c4, c5, c6, c7] = pick_hues(&hues)?;
+        Ok(Palette { colors: [background, foreground, c1, c2, c3, c4, c5, c6, c7] })
+    }
+}
+
+/// Converts an Alacritty theme, in TOML or the older YAML format, to a palette.
+///
+/// Uses `colors.primary`, `colors.normal` and `colors.bright`.
+pub fn import_alacritty(s: &str) -> Result<Palette> {
+    let theme: serde_json::Value = match toml::from_str::<toml::Value>(s) {
+        Ok(theme) => serde_json::to_value(theme).map_err(|e| Error::Import(e.to_string()))?,
+        Err(_) => serde_yaml::from_str(s).map_err(|e| Error::Import(e.to_string()))?,
+    };
+    let colors = &theme["colors"];
+    let mut terminal_colors = TerminalColors::default();
+    let mut set = |key: &str, value: &serde_json::Value| match value {
+        serde_json::Value::Null => Ok(()),
+        serde_json::Value::String(s) => terminal_colors.set(key, s),
+        // An unquoted `0x1d1f21` is an integer in YAML and TOML.
+        serde_json::Value::Number(n) => match n.as_u64() {
+            Some(n) => terminal_colors.set(key, &format!("{:06x}", n)),
+            None => Err(Error::Import(format!("invalid color for `{}`: {}", key, n))),
+        },
+        value => Err(Error::Import(format!("invalid color for `{}`: {}", key, value))),
+    };
+    for key in ["background", "foreground"] {
+        set(key, &colors["primary"][key])?;
+    }
+    for (offset, group) in [(0, "normal"), (8, "bright")] {
+        for (i, name) in ANSI_NAMES.iter().enumerate() {
+            set(&(offset + i).to_string(), &colors[group][name])?;
+        }
+    }
+    terminal_colors.to_palette()
+}
+
+/// Converts the `background`, `foreground` and `color0` to `color15` lines of a kitty config to a palette.
+pub fn import_kitty(s: &str) -> Result<Palette> {
+    let mut terminal_colors = TerminalColors::default();
+    for line in s.lines().map(str::trim).filter(|line| !line.starts_with('#')) {
+        if let Some((key, value)) = line.split_once(char::is_whitespace) {
+            terminal_colors.set(key.strip_prefix("color").unwrap_or(key), value.trim())?;
+        }
+    }
+    terminal_colors.to_palette()
+}
+
+/// Converts the `*background`, `*foreground` and `*color0` to `*color15`
+/// resources of an Xresources file to a palette.
+///
+/// Resources for a single program like `URxvt.color1` and `#define` constants
+/// are accepted. Of `#ifdef`/`#ifndef` blocks, only the branches taken with
+/// the constants defined in `s` are read, `#if` blocks and `#include` are skipped.
+pub fn import_xresources(s: &str) -> Result<Palette> {
+    let mut defines: Vec<(&str, &str)> = Vec::new();
+    // Whether each enclosing conditional block is read.
+    let mut branches: Vec<bool> = Vec::new();
+    let mut terminal_colors = TerminalColors::default();
+    for line in s.lines().map(str::trim).filter(|line| !line.starts_with('!')) {
+        if let Some(directive) = line.strip_prefix('#') {
+            let (directive, arg) = directive.trim_start().split_once(char::is_whitespace).unwrap_or((directive.trim_start(), ""));
+            let arg = arg.trim();
+            let is_defined = |name: &str| defines.iter().any(|(define, _)| *define == name);
+            match directive {
+                "ifdef" => branches.push(is_defined(arg)),
+                "ifndef" => branches.push(!is_defined(arg)),
+                "if" => branches.push(false),
+                "else" => {
+                    if let Some(branch) = branches.last_mut() {
+                        *branch = !*branch;
+                    }
+                },
+                "endif" => { branches.pop(); },
+                "define" if branches.iter().all(|x| *x) => {
+                    if let Some((name, value)) = arg.split_once(char::is_whitespace) {
+                        defines.push((name, value.trim()));
+                    }
+                },
+                _ => {},
+            }
+            continue;
+        }
+        if !branches.iter().all(|x| *x) {
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let key = key.trim().rsplit(['*', '.']).next().unwrap_or_default();
+        let value = value.trim();
+        let value = defines.iter().rev().find(|(name, _)| *name == value).map_or(value, |(_, value)| value);
+        terminal_colors.set(key.strip_prefix("color").unwrap_or(key), value)?;
+    }
+    terminal_colors.to_palette()
+}
+
+/// A value of a plist, only with what color schemes use.
+#[derive(Debug)]
+enum Plist {
+    Dict(Vec<(String, Plist)>),
+    Number(f32),
+    Other,
+}
+
+impl Plist {
+    fn get(&self, key: &str) -> Option<&Plist> {
+        match self {
+            Plist::Dict(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+}
+
+/// Parses XML plists, whose tags never have attributes except the root.
+struct PlistParser<'a> {
+    s: &'a str,
+}
+
+impl<'a> PlistParser<'a> {
+    /// Returns the next tag name, e.g. `dict`, `/dict` or `true/`, and the text before it.
+    fn next_tag(&mut self) -> Option<(&'a str, &'a str)> {
+        loop {
+            let start = self.s.find('<')?;
+            let end = start + self.s[start..].find('>')?;
+            let text = &self.s[..start];
+            let tag = &self.s[start + 1..end];
+            self.s = &self.s[end + 1..];
+            if !tag.starts_with('?') && !tag.starts_with('!') {
+                return Some((tag.split_whitespace().next().unwrap_or_default(), text));
+            }
+        }
+    }
+
+    fn error(&self, message: &str) -> Error {
+        Error::Import(format!("invalid plist: {}", message))
+    }
+
+    /// Parses the value whose opening tag is `tag`.
+    fn value(&mut self, tag: &str) -> Result<Plist> {
+        match tag {
+            "dict" => {
+                let mut entries = Vec::new();
+                loop {
+                    match self.next_tag() {
+                        Some(("/dict", _)) => return Ok(Plist::Dict(entries)),
+                        Some(("key", _)) => {
+                            let key = match self.next_tag() {
+                                Some(("/key", key)) => key.trim().to_string(),
+                                _ => return Err(self.error("expecting </key>")),
+                            };
+                            let tag = self.next_tag().ok_or_else(|| self.error("missing value"))?.0;
+                            entries.push((key, self.value(tag)?));
+                        },
+                        _ => return Err(self.error("expecting <key> or </dict>")),
+                    }
+                }
+            },
+            "array" => {
+                loop {
+                    match self.next_tag() {
+                        Some(("/array", _)) => return Ok(Plist::Other),
+                        Some((tag, _)) => { self.value(tag)?; },
+                        None => return Err(self.error("missing </array>")),
+                    }
+                }
+            },
+            tag if tag.ends_with('/') => Ok(Plist::Other),
+            tag => {
+                let (end, text) = self.next_tag().ok_or_else(|| self.error(&format!("missing </{}>", tag)))?;
+                if end.strip_prefix('/') != Some(tag) {
+                    return Err(self.error(&format!("expecting </{}>", tag)));
+                }
+                match tag {
+                    "real" | "integer" => text.trim().parse().map(Plist::Number).map_err(|_| self.error(&format!("invalid number `{}`", text))),
+                    _ => Ok(Plist::Other),
+                }
+            },
+        }
+    }
+}
+
+/// Converts an iTerm2 `.itermcolors` file to a palette.
+///
+/// Components are read as sRGB whatever the `Color Space` of a color, so the
+/// colors of `Calibrated` or `P3` files are slightly off.
+pub fn import_iterm2(s: &str) -> Result<Palette> {
+    let mut parser = PlistParser { s };
+    let root = loop {
+        match parser.next_tag() {
+            Some(("plist", _)) => continue,
+            Some((tag, _)) => break parser.value(tag)?,
+            None => return Err(parser.error("empty")),
+        }
+    };
+    let mut terminal_colors = TerminalColors::default();
+    let keys = ["Background Color".to_string(), "Foreground Color".to_string()].into_iter()
+        .chain((0..16).map(|i| format!("Ansi {} Color", i)));
+    let slots = ["background".to_string(), "foreground".to_string()].into_iter()
+        .chain((0..16).map(|i| i.to_string()));
+    for (key, slot) in keys.zip(slots) {
+        let Some(color) = root.get(&key) else {
+            continue;
+        };
+        let component = |name: &str| match color.get(&format!("{} Component", name)) {
+            Some(Plist::Number(x)) => Ok((x.clamp(0., 1.) * 255.).round() as u8),
+            _ => Err(Error::Import(format!("missing {} component of `{}`", name, key))),
+        };
+        terminal_colors.set_color(&slot, Color::new(component("Red")?, component("Green")?, component("Blue")?));
+    }
+    terminal_colors.to_palette()
+}
+
+/// Removes the `//` and `/* */` comments of JSON, keeping the ones in strings.
+fn strip_json_comments(s: &str) -> String {
+    let mut stripped = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('\\', Some(_)) if in_string => {
+                stripped.push(c);
+                stripped.extend(chars.next());
+                continue;
+            },
+            ('"', _) => in_string = !in_string,
+            ('/', Some('/')) if !in_string => {
+                while chars.next_if(|c| *c != '\n').is_some() {}
+                continue;
+            },
+            ('/', Some('*')) if !in_string => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+                stripped.push(' ');
+                continue;
+            },
+            _ => {},
+        }
+        stripped.push(c);
+    }
+    stripped
+}
+
+/// Converts a Windows Terminal color scheme to a palette. A `settings.json`
+/// is accepted too, its first scheme is used. Comments are ignored.
+pub fn import_windows_terminal(s: &str) -> Result<Palette> {
+    let theme: serde_json::Value = serde_json::from_str(&strip_json_comments(s)).map_err(|e| Error::Import(e.to_string()))?;
+    let scheme = theme.get("schemes").and_then(|schemes| schemes.get(0)).unwrap_or(&theme);
+    let mut terminal_colors = TerminalColors::default();
+    for key in ["background", "foreground"] {
+        if let Some(value) = scheme[key].as_str() {
+            terminal_colors.set(key, value)?;
+        }
+    }
+    for (i, name) in ANSI_NAMES.iter().enumerate() {
+        let name = if *name == "magenta" { "purple" } else { name };
+        let bright = format!("bright{}{}", name[..1].to_uppercase(), &name[1..]);
+        for (offset, key) in [(0, name), (8, bright.as_str())] {
+            if let Some(value) = scheme[key].as_str() {
+                terminal_colors.set(&(offset + i).to_string(), value)?;
+            }
+        }
+    }
+    terminal_colors.to_palette()
+}
+
+#[test]
+fn import_base16_works() {
+    let gruvbox = r#"
+scheme: "Gruvbox dark, hard"
+author: "Dawid Kurek (dawikur@gmail.com)"
+base00: "1d2021"
+base01: "3c3836"
+base02: "504945"
+base03: "665c54"
+base04: "bdae93"
+base05: "d5c4a1"
+base06: "ebdbb2"
+base07: "fbf1c7"
+base08: "fb4934"
+base09: "fe8019"
+base0A: "fabd2f"
+base0B: "b8bb26"
+base0C: "8ec07c"
+base0D: "83a598"
+base0E: "d3869b"
+base0F: "d65d0e"
+"#;
+    let palette = import_base16(gruvbox).unwrap();
+    // base0F is dropped, being close to the orange of base09.
+    assert_eq!(palette.to_string(), "1d2021-d5c4a1-fb4934-fe8019-fabd2f-b8bb26-8ec07c-83a598-d3869b");
+
+    let tinted = r##"
+system: "base24"
+name: "Example"
+variant: "light"
+palette:
+  base00: "#fafafa"
+  base01: "#f0f0f0"
+  base02: "#e5e5e5"
+  base03: "#a0a1a7"
+  base04: "#696c77"
+  base05: "#383a42"
+  base06: "#202227"
+  base07: "#090a0b"
+  base08: "#e45649"
+  base09: "#986801"
+  base0A: "#c18401"
+  base0B: "#50a14f"
+  base0C: "#0184bc"
+  base0D: "#4078f2"
+  base0E: "#a626a4"
+  base0F: "#986801"
+  base10: "#ffffff"
+  base12: "#ff6b5d"
+"##;
+    let palette = import_base16(tinted).unwrap();
+    assert_eq!(palette.to_string(), "fafafa-383a42-e45649-c18401-986801-50a14f-0184bc-4078f2-a626a4");
+
+    let unquoted = "
+base00: 181818
+base05: d8d8d8
+base08: ab4642
+base09: dc9656
+base0A: f7ca88
+base0B: a1b56c
+base0C: 86c1b9
+base0D: 7cafc2
+base0E: ba8baf
+base0F: a16946
+";
+    let palette = import_base16(unquoted).unwrap();
+    assert_eq!(palette.to_string(), "181818-d8d8d8-ab4642-a16946-f7ca88-a1b56c-86c1b9-7cafc2-ba8baf");
+
+    assert!(matches!(import_base16("base00: '000000'"), Err(Error::Import(e)) if e == "missing `base08`"));
+    let invalid = unquoted.replace("base00: 181818", "base00: [0]");
+    assert!(matches!(import_base16(&invalid), Err(Error::Import(e)) if e == "invalid color for `base00`: - 0"));
+}
+
+#[test]
+fn import_alacritty_works() {
+    let toml = r#"
+# Colors (Tomorrow Night)
+[colors.primary]
+background = '#1d1f21'
+foreground = '#c5c8c6'
+
+[colors.cursor]
+text = '#1d1f21'
+cursor = '#ffffff'
+
+# Normal colors
+[colors.normal]
+black   = '#1d1f21'
+red     = '#cc6666'
+green   = '#b5bd68'
+yellow  = '#f0c674'
+blue    = '#81a2be'
+magenta = '#b294bb'
+cyan    = '#8abeb7'
+white   = '#c5c8c6'
+
+# Bright colors
+[colors.bright]
+black   = '#666666'
+red     = '#d54e53'
+green   = '#b9ca4a'
+yellow  = '#e7c547'
+blue    = '#7aa6da'
+magenta = '#c397d8'
+cyan    = '#70c0b1'
+white   = '#eaeaea'
+"#;
+    let yaml = r#"
+# Colors (Tomorrow Night)
+colors:
+  # Default colors
+  primary:
+    background: '0x1d1f21'
+    foreground: '0xc5c8c6'
+
+  # Colors the cursor will use if `custom_cursor_colors` is true
+  cursor:
+    text: '0x1d1f21'
+    cursor: '0xffffff'
+
+  # Normal colors
+  normal:
+    black:   '0x1d1f21'
+    red:     '0xcc6666'
+    green:   '0xb5bd68'
+    yellow:  '0xf0c674'
+    blue:    '0x81a2be'
+    magenta: '0xb294bb'
+    cyan:    '0x8abeb7'
+    white:   '0xc5c8c6'
+
+  # Bright colors
+  bright:
+    black:   '0x666666'
+    red:     '0xd54e53'
+    green:   '0xb9ca4a'
+    yellow:  '0xe7c547'
+    blue:    '0x7aa6da'
+    magenta: '0xc397d8'
+    cyan:    '0x70c0b1'
+    white:   '0xeaeaea'
+"#;
+    let palette = "1d1f21-c5c8c6-cc6666-f0c674-e7c547-b9ca4a-70c0b1-7aa6da-c397d8";
+    assert_eq!(import_alacritty(toml).unwrap().to_string(), palette);
+    assert_eq!(import_alacritty(yaml).unwrap().to_string(), palette);
+    let unquoted = yaml.replace('\'', "");
+    assert!(unquoted.contains("background: 0x1d1f21\n"));
+    assert_eq!(import_alacritty(&unquoted).unwrap().to_string(), palette);
+    assert!(matches!(
+        import_alacritty("colors:\n  primary:\n    background: true"),
+        Err(Error::Import(e)) if e == "invalid color for `background`: true"));
+}
+
+#[test]
+fn import_kitty_works() {
+    let conf = r#"
+# vim:ft=kitty
+
+## name: Dracula
+## author: Keith Simmons
+## license: MIT
+
+font_family      JetBrains Mono
+font_size 11.0
+background_opacity 0.95
+
+# The basic colors
+foreground              #f8f8f2
+background              #282a36
+selection_foreground    #ffffff
+selection_background    #44475a
+
+# Cursor colors
+cursor            #f8f8f2
+cursor_text_color background
+
+# URL underline color when hovering with mouse
+url_color #8be9fd
+
+# black
+color0  #21222c
+color8  #6272a4
+
+# red
+color1  #ff5555
+color9  #ff6e6e
+
+# green
+color2  #50fa7b
+color10 #69ff94
+
+# yellow
+color3  #f1fa8c
+color11 #ffffa5
+
+# blue
+color4  #bd93f9
+color12 #d6acff
+
+# magenta
+color5  #ff79c6
+color13 #ff92df
+
+# cyan
+color6  #8be9fd
+color14 #a4ffff
+
+# white
+color7  #f8f8f2
+color15 #ffffff
+
+map ctrl+shift+enter new_window_with_cwd
+"#;
+    assert_eq!(import_kitty(conf).unwrap().to_string(), "282a36-f8f8f2-ff5555-f1fa8c-50fa7b-a4ffff-8be9fd-d6acff-ff79c6");
+    assert!(matches!(import_kitty("background #000000"), Err(Error::Import(_))));
+}
+
+#[test]
+fn import_xresources_works() {
+    let xresources = r#"
+! Copyright (c) 2016-present Arctic Ice Studio <development@arcticicestudio.com>
+! Copyright (c) 2016-present Sven Greb <development@svengreb.de>
+
+! Project:    Nord XResources
+! Version:    0.1.0
+
+#define nord0 #2E3440
+#define nord1 #3B4252
+#define nord3 #4C566A
+#define nord4 #D8DEE9
+#define nord6 #ECEFF4
+#define nord7 #8FBCBB
+#define nord8 #88C0D0
+#define nord9 #81A1C1
+#define nord11 #BF616A
+#define nord12 #D08770
+#define nord13 #EBCB8B
+#define nord14 #A3BE8C
+#define nord15 #B48EAD
+
+#include ".Xresources.d/fonts"
+
+URxvt.font: xft:Hack:size=10
+Xft.dpi: 96
+
+*.foreground:   nord4
+#ifdef background_opacity
+*.background:   [background_opacity]nord0
+#else
+*.background:   nord0
+#endif
+*.cursorColor:  nord4
+*fading: 35
+*fadeColor: nord3
+
+*.color0: nord1
+*.color1: nord11
+*.color2: nord14
+*.color3: nord13
+*.color4: nord9
+*.color5: nord15
+*.color6: nord8
+*.color7: #E5E9F0
+*.color8: nord3
+*.color9: nord12
+*.color10: nord14
+*.color11: nord13
+*.color12: nord9
+*.color13: nord15
+*.color14: nord7
+*.color15: nord6
+"#;
+    assert_eq!(import_xresources(xresources).unwrap().to_string(), "2e3440-d8dee9-bf616a-d08770-ebcb8b-a3be8c-88c0d0-81a1c1-b48ead");
+    assert_eq!(parse_color("rgb:f/8/0"), Some(Color::new(255, 136, 0)));
+    assert_eq!(parse_color("rgb:ffff/8888/0000"), Some(Color::new(255, 136, 0)));
+    assert_eq!(parse_color("rgb:fffff/0/0"), None);
+    assert!(matches!(import_xresources("*background: #000000\n*color1: rgb:fffffff/0/0\n"), Err(Error::Import(_))));
+}
+
+#[test]
+fn import_iterm2_works() {
+    let plist = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>Ansi 1 Color</key>
+	<dict>
+		<key>Alpha Component</key>
+		<real>1</real>
+		<key>Blue Component</key>
+		<real>0.4000000059604645</real>
+		<key>Color Space</key>
+		<string>sRGB</string>
+		<key>Green Component</key>
+		<real>0.4000000059604645</real>
+		<key>Red Component</key>
+		<real>0.800000011920929</real>
+	</dict>
+	<key>Ansi 2 Color</key>
+	<dict>
+		<key>Alpha Component</key>
+		<real>1</real>
+		<key>Blue Component</key>
+		<real>0.40784314274787903</real>
+		<key>Color Space</key>
+		<string>sRGB</string>
+		<key>Green Component</key>
+		<real>0.7411764860153198</real>
+		<key>Red Component</key>
+		<real>0.7098039388656616</real>
+	</dict>
+	<key>Ansi 3 Color</key>
+	<dict>
+		<key>Alpha Component</key>
+		<real>1</real>
+		<key>Blue Component</key>
+		<real>0.45490196347236633</real>
+		<key>Color Space</key>
+		<string>sRGB</string>
+		<key>Green Component</key>
+		<real>0.7764706015586853</real>
+		<key>Red Component</key>
+		<real>0.9411764740943909</real>
+	</dict>
+	<key>Ansi 4 Color</key>
+	<dict>
+		<key>Alpha Component</key>
+		<real>1</real>
+		<key>Blue Component</key>
+		<real>0.7450980544090271</real>
+		<key>Color Space</key>
+		<string>sRGB</string>
+		<key>Green Component</key>
+		<real>0.6352941393852234</real>
+		<key>Red Component</key>
+		<real>0.5058823823928833</real>
+	</dict>
+	<key>Ansi 5 Color</key>
+	<dict>
+		<key>Alpha Component</key>
+		<real>1</real>
+		<key>Blue Component</key>
+		<real>0.7333333492279053</real>
+		<key>Color Space</key>
+		<string>sRGB</string>
+		<key>Green Component</key>
+		<real>0.5803921818733215</real>
+		<key>Red Component</key>
+		<real>0.6980392336845398</real>
+	</dict>
+	<key>Ansi 6 Color</key>
+	<dict>
+		<key>Alpha Component</key>
+		<real>1</real>
+		<key>Blue Component</key>
+		<real>0.7176470756530762</real>
+		<key>Color Space</key>
+		<string>sRGB</string>
+		<key>Green Component</key>
+		<real>0.7450980544090271</real>
+		<key>Red Component</key>
+		<real>0.5411764979362488</real>
+	</dict>
+	<key>Ansi 9 Color</key>
+	<dict>
+		<key>Alpha Component</key>
+		<real>1</real>
+		<key>Blue Component</key>
+		<real>0.32549020648002625</real>
+		<key>Color Space</key>
+		<string>sRGB</string>
+		<key>Green Component</key>
+		<real>0.30588236451148987</real>
+		<key>Red Component</key>
+		<real>0.8352941274642944</real>
+	</dict>
+	<key>Background Color</key>
+	<dict>
+		<key>Alpha Component</key>
+		<real>1</real>
+		<key>Blue Component</key>
+		<real>0.12941177189350128</real>
+		<key>Color Space</key>
+		<string>sRGB</string>
+		<key>Green Component</key>
+		<real>0.12156862765550613</real>
+		<key>Red Component</key>
+		<real>0.11372549086809158</real>
+	</dict>
+	<key>Cursor Color</key>
+	<dict>
+		<key>Alpha Component</key>
+		<real>1</real>
+		<key>Blue Component</key>
+		<real>0.6784313917160034</real>
+		<key>Color Space</key>
+		<string>sRGB</string>
+		<key>Green Component</key>
+		<real>0.686274528503418</real>
+		<key>Red Component</key>
+		<real>0.6823529601097107</real>
+	</dict>
+	<key>Foreground Color</key>
+	<dict>
+		<key>Alpha Component</key>
+		<real>1</real>
+		<key>Blue Component</key>
+		<real>0.7764706015586853</real>
+		<key>Color Space</key>
+		<string>sRGB</string>
+		<key>Green Component</key>
+		<real>0.7843137383460999</real>
+		<key>Red Component</key>
+		<real>0.772549033164978</real>
+	</dict>
+	<key>Use Bright Bold</key>
+	<true/>
+</dict>
+</plist>
+"#;
+    assert_eq!(import_iterm2(plist).unwrap().to_string(), "1d1f21-c5c8c6-cc6666-d54e53-f0c674-b5bd68-8abeb7-81a2be-b294bb");
+    assert!(matches!(import_iterm2("<plist><dict><key>a</key></plist>"), Err(Error::Import(_))));
+}
+
+#[test]
+fn import_windows_terminal_works() {
+    let settings = r##"
+// This file was initially generated by Windows Terminal 1.3.2651.0
+// It should still be usable in newer versions, but newer versions might have additional
+// settings, help text, or changes that you will not see unless you clear this file
+// and let us generate a new one for you.
+
+// To view the default settings, hover over "settings.json" and look at the defaults tab.
+{
+    "$schema": "https://aka.ms/terminal-profiles-schema",
+
+    "defaultProfile": "{61c54bbd-c2c6-5271-96e7-009a87ff44bf}",
+
+    // You can add more global application settings here.
+    // To learn more about global settings, visit https://aka.ms/terminal-global-settings
+
+    "profiles":
+    {
+        "defaults":
+        {
+            // Put settings here that you want to apply to all profiles.
+        },
+        "list":
+        [
+            {
+                // Make changes here to the powershell.exe profile.
+                "guid": "{61c54bbd-c2c6-5271-96e7-009a87ff44bf}",
+                "name": "Windows PowerShell",
+                "commandline": "powershell.exe",
+                "hidden": false
+            }
+        ]
+    },
+
+    // Add custom color schemes to this array.
+    // To learn more about color schemes, visit https://aka.ms/terminal-color-schemes
+    "schemes": [
+        {
+            "name" : "Campbell",
+            "background" : "#0C0C0C",
+            "foreground" : "#CCCCCC",
+            "black" : "#0C0C0C",
+            "red" : "#C50F1F",
+            "green" : "#13A10E",
+            "yellow" : "#C19C00",
+            "blue" : "#0037DA",
+            "purple" : "#881798",
+            "cyan" : "#3A96DD",
+            "white" : "#CCCCCC",
+            "brightBlack" : "#767676",
+            "brightRed" : "#E74856",
+            "brightGreen" : "#16C60C",
+            "brightYellow" : "#F9F1A5",
+            "brightBlue" : "#3B78FF",
+            "brightPurple" : "#B4009E",
+            "brightCyan" : "#61D6D6",
+            "brightWhite" : "#F2F2F2",
+            "cursorColor": "#FFFFFF", /* not used */
+            "selectionBackground": "#FFFFFF"
+        }
+    ],
+
+    // Add custom actions and keybindings to this array.
+    // To unbind a key combination from your defaults.json, set the command to "unbound".
+    // To learn more about actions and keybindings, visit https://aka.ms/terminal-keybindings
+    "actions": []
+}
+"##;
+    assert_eq!(import_windows_terminal(settings).unwrap().to_string(), "0c0c0c-cccccc-c50f1f-c19c00-16c60c-61d6d6-3a96dd-0037da-881798");
+    assert!(import_windows_terminal(r##"{"background": "#000000" /* unterminated"##).is_err());
+}
@@ -4,20 +4,63 @@ mod config;
 mod color_science;
 mod generator;
 mod palette;
+mod palette_file;
+mod error;
+mod expression;
+mod contrast;
+mod css_color;
+mod import;
 
 pub type Color = ext_palette::Srgb<u8>;
-pub use palette::Palette;
+pub use palette::{Palette, PaletteOption};
+pub use palette_file::{PaletteFile, Variant};
+pub use config::{Config, VariableOrder};
+pub use color_science::ColorSpace;
+pub use generator::{counterpart, generate, generate_seeded, generate_with_rng, generate_constrained, generate_with_options, GeneratorOptions, ContrastConstraints, ContrastViolation};
+pub use error::{Error, Result};
+pub use contrast::{WcagLevel, apca_lc, wcag_ratio};
+pub use import::{import_alacritty, import_base16, import_iterm2, import_kitty, import_windows_terminal, import_xresources};
 
+/// Panics on error, see `try_to_data`.
 pub fn to_data(palette: &Palette) -> serde_json::Value {
-    let config = config::Config::from_palette(*palette);
-    let variables = base9::get_variables(&config).unwrap();
-    base9::format_variables(&config, &variables)
+    try_to_data(palette).unwrap()
 }
 
+/// Panics on error, see `try_to_mustache_data`.
 pub fn to_mustache_data(palette: &Palette) -> mustache::Data {
-    let config = config::Config::from_palette(*palette);
-    let variables = base9::get_variables(&config).unwrap();
-    mustache::to_data(base9::format_variables(&config, &variables)).unwrap()
+    try_to_mustache_data(palette).unwrap()
+}
+
+pub fn try_to_data(palette: &Palette) -> Result<serde_json::Value> {
+    to_data_with_config(&Config::from_palette(*palette))
+}
+
+pub fn try_to_mustache_data(palette: &Palette) -> Result<mustache::Data> {
+    Ok(mustache::to_data(try_to_data(palette)?).map_err(mustache::Error::Encoder)?)
+}
+
+/// Same as `try_to_data` but uses all settings of `config` instead of only its palette.
+pub fn to_data_with_config(config: &Config) -> Result<serde_json::Value> {
+    let variables = base9::get_variables(config)?;
+    Ok(base9::format_variables(config, &variables))
+}
+
+/// Returns every unknown or cyclic reference among the aliases of `config`.
+/// An empty result means `to_data_with_config` won't fail because of them.
+pub fn validate_config(config: &Config) -> Vec<Error> {
+    base9::validate(config)
+}
+
+/// Returns the variables of `config` that were outside of sRGB and had their
+/// chroma reduced to fit, e.g. `c1.p125`. Aliases are listed by their name.
+pub fn out_of_gamut_colors(config: &Config) -> Vec<String> {
+    base9::out_of_gamut(config)
+}
+
+/// Renders the mustache template `template_str` with the data of `config`.
+pub fn render_string(config: &Config, template_str: &str) -> Result<String> {
+    let data = to_data_with_config(config)?;
+    Ok(mustache::compile_str(template_str)?.render_to_string(&data)?)
 }
 
 // When the `wee_alloc` feature is enabled, use `wee_alloc` as the global
@@ -25,5 +68,3 @@ pub fn to_mustache_data(palette: &Palette) -> mustache::Data {
 // #[cfg(feature = "wee_alloc")]
 // #[global_allocator]
 // static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
-
-
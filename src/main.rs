@@ -1,7 +1,7 @@
-
-use base9::{get_variables, format_variables};
-use config::{Config};
+use base9_builder::{counterpart, ColorSpace, Config, VariableOrder, PaletteFile, PaletteOption, GeneratorOptions, ContrastConstraints, generate, generate_with_options, import_alacritty, import_base16, import_iterm2, import_kitty, import_windows_terminal, import_xresources, out_of_gamut_colors, to_data_with_config, validate_config, wcag_ratio, Color, WcagLevel};
 use clap::{arg, Command, ArgMatches, Arg};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
 use std::io::{self, Read};
 use std::path::PathBuf;
 use std::str::FromStr;
@@ -9,14 +9,6 @@ use anyhow::{Result, anyhow};
 use mustache::{compile_path, compile_str};
 
 mod utils;
-mod color_science;
-mod base9;
-mod config;
-mod palette;
-mod generator;
-pub type Color = ext_palette::Srgb<u8>;
-
-pub const N: usize = 9;
 
 fn read_stdin() -> Result<String> {
     let mut buf = String::new();
@@ -26,57 +18,245 @@ fn read_stdin() -> Result<String> {
 }
 
 fn cli() -> Command<'static> {
-    let palette_arg: Arg = arg!(<PALETTE> "the palette code. Use `-` for default palette.");
+    let palette_arg: Arg = arg!(<PALETTE> "the palette code. Use `-` for the palette of `--palette-file`, or else of config.");
+    let seed_arg: Arg = arg!(--seed <SEED> "seed of the random generator, for reproducible output. Overrides `@<seed>` in the palette code.")
+        .required(false)
+        .value_parser(clap::value_parser!(u64));
     Command::new("base9-builder")
         .about("base9 CLI tool")
         .subcommand_required(true)
         .arg_required_else_help(true)
+        .arg(
+            arg!(-c --config <CONFIG> "path to a YAML or JSON config file, merged onto the default config.")
+            .required(false)
+            .global(true)
+            .value_parser(clap::value_parser!(PathBuf)))
+        .arg(
+            arg!(--"palette-file" <PALETTE_FILE> "path to a YAML, JSON or TOML palette file, used when the palette code is `-`.")
+            .required(false)
+            .global(true)
+            .value_parser(clap::value_parser!(PathBuf)))
+        .arg(
+            arg!(--order <ORDER> "order of variables given to templates. Overrides `order` in config.")
+            .required(false)
+            .global(true)
+            .value_parser(["declaration", "alphabetical"]))
+        .arg(
+            arg!(--"color-space" <COLOR_SPACE> "color space of shades and `mix()`. Overrides `color_space` in config.")
+            .required(false)
+            .global(true)
+            .value_parser(["lab_contrast", "oklab", "oklch", "linear_srgb"]))
+        .arg(arg!(--"warn-gamut" "prints the colors that were out of sRGB gamut and had to be mapped into it").global(true))
+        .arg(arg!(--name <NAME> "theme name given to templates as `NAME`. Overrides `name` in config.").required(false).global(true))
+        .arg(arg!(--slug <SLUG> "given to templates as `SLUG`, `NAME` in kebab-case by default. Overrides `slug` in config.").required(false).global(true))
+        .arg(arg!(--author <AUTHOR> "given to templates as `AUTHOR`. Overrides `author` in config.").required(false).global(true))
         .subcommand(
             Command::new("render")
                 .about("renders theme template")
                 .arg(palette_arg.clone())
+                .arg(seed_arg.clone())
                 .arg(arg!(<TEMPLATE> "path to template file. Use `-` to read from stdin."))
                 .arg(
                     arg!([DEST] "path to write output to. By default output to stdout.")
                     .value_parser(clap::value_parser!(std::path::PathBuf)))
         )
-        // .subcommand(
-        //     Command::new("generate")
-        //         .about("randomly generates a base9 palette")
-        //         .arg(arg!(<INCOMPLETE_PALETTE> "Palette code but omit colors that you want to be generated. (For example: -ffffff-------"))
-        // )
+        .subcommand(
+            Command::new("generate")
+                .about("randomly generates base9 palettes")
+                .arg(arg!(<INCOMPLETE_PALETTE> "Palette code but omit colors that you want to be generated. (For example: `_-ffffff-?` or `fg=ffffff`)"))
+                .arg(
+                    arg!(-n --count <COUNT> "number of palettes to generate")
+                    .required(false)
+                    .default_value("1")
+                    .value_parser(clap::value_parser!(usize)))
+                .arg(seed_arg.clone())
+                .arg(arg!(--dark "generate a dark background and light foreground").conflicts_with("light"))
+                .arg(arg!(--light "generate a light background and dark foreground"))
+                .arg(
+                    arg!(--"hue-l" <L> "Lab lightness range of generated c1 to c7, 0-100. (For example: `--hue-l 75 85`)")
+                    .required(false)
+                    .number_of_values(2)
+                    .value_parser(clap::value_parser!(f32)))
+                .arg(
+                    arg!(--"hue-c" <C> "Lab chroma range of generated c1 to c7, low values are muted. (For example: `--hue-c 10 25`)")
+                    .required(false)
+                    .number_of_values(2)
+                    .value_parser(clap::value_parser!(f32)))
+                .arg(
+                    arg!(--"hue-start" <DEGREES> "Lab hue angle of the first generated hue, when no hue is given")
+                    .required(false)
+                    .value_parser(clap::value_parser!(f32)))
+                .arg(
+                    arg!(--"hue-spacing" <DEGREES> "degrees between generated hues when at most one hue is given, 360/7 by default")
+                    .required(false)
+                    .value_parser(clap::value_parser!(f32)))
+                .arg(
+                    arg!(--"min-contrast" <RATIO> "minimum WCAG contrast of c1 to c7 against background, e.g. 4.5")
+                    .required(false)
+                    .value_parser(clap::value_parser!(f32)))
+                .arg(
+                    arg!(--"min-fg-contrast" <RATIO> "minimum WCAG contrast of foreground against background, e.g. 7")
+                    .required(false)
+                    .value_parser(clap::value_parser!(f32)))
+                .arg(
+                    arg!(--format <FORMAT> "`code` prints the palette code, `preview` prints a preview table, `json` prints all variables")
+                    .required(false)
+                    .default_value("code")
+                    .value_parser(["code", "preview", "json"]))
+        )
         .subcommand(
             Command::new("preview")
                 .about("prints a table of all generated colors to preview")
                 .arg(palette_arg.clone())
+                .arg(seed_arg.clone())
+                .arg(arg!(--ansi256 "use the nearest xterm-256 colors, for terminals without truecolor"))
+        )
+        .subcommand(
+            Command::new("counterpart")
+                .about("prints the palette of opposite polarity: light for a dark palette and vice versa")
+                .arg(palette_arg.clone())
+                .arg(seed_arg.clone())
+        )
+        .subcommand(
+            Command::new("contrast")
+                .about("prints the WCAG contrast of foreground and hue shades against background")
+                .arg(palette_arg.clone())
+                .arg(seed_arg.clone())
+        )
+        .subcommand(
+            Command::new("import")
+                .about("prints the palette code of a theme of another format")
+                .arg(
+                    arg!(<FORMAT> "format of the theme. `base16` also reads base24 schemes, `alacritty` reads TOML and YAML.")
+                    .value_parser(["base16", "alacritty", "kitty", "xresources", "iterm2", "windows-terminal"]))
+                .arg(arg!(<FILE> "path to the theme. Use `-` to read from stdin.").value_parser(clap::value_parser!(PathBuf)))
+        )
+        .subcommand(
+            Command::new("validate-config")
+                .about("checks that every alias in config references an existing color")
         )
         .subcommand(
             Command::new("list-variables")
                 .hide(true)
                 .about("prints all variables used by templates")
-                .arg(palette_arg.clone())
+                .arg(palette_arg)
+                .arg(seed_arg)
         )
 }
 
-fn matches_to_formatted_variables(matches: &ArgMatches) -> Result<serde_json::Value> {
-    let mut config = Config::default();
-    let palette_arg: &str = matches.get_one::<String>("PALETTE").ok_or(anyhow!("missing palette!"))?;
+/// The palette file of `--palette-file`, read only when the palette code `id` is `-`.
+fn matches_to_palette_file(matches: &ArgMatches, id: &str) -> Result<Option<PaletteFile>> {
+    if matches.get_one::<String>(id).map(String::as_str) != Some("-") {
+        return Ok(None);
+    }
+    Ok(match matches.get_one::<PathBuf>("palette-file") {
+        Some(path) => Some(PaletteFile::from_path(path)?),
+        None => None,
+    })
+}
+
+fn matches_to_palette_option(matches: &ArgMatches, id: &str, palette_file: Option<&PaletteFile>) -> Result<PaletteOption> {
+    let palette_arg: &str = matches.get_one::<String>(id).ok_or_else(|| anyhow!("missing palette!"))?;
+    let mut palette_option = match palette_file {
+        Some(palette_file) => palette_file.to_palette_option(),
+        None => PaletteOption::from_str(palette_arg)?,
+    };
+    if let Some(seed) = matches.get_one::<u64>("seed") {
+        palette_option.seed = Some(*seed);
+    }
+    Ok(palette_option)
+}
+
+fn matches_to_generator_options(matches: &ArgMatches) -> GeneratorOptions {
+    let range = |id: &str| matches.get_many::<f32>(id).map(|mut values| {
+        let start = *values.next().unwrap();
+        start..=*values.next().unwrap()
+    });
+    GeneratorOptions {
+        is_dark: match (matches.contains_id("dark"), matches.contains_id("light")) {
+            (true, _) => Some(true),
+            (_, true) => Some(false),
+            _ => None,
+        },
+        hue_l: range("hue-l"),
+        hue_c: range("hue-c"),
+        hue_start: matches.get_one::<f32>("hue-start").copied(),
+        hue_spacing: matches.get_one::<f32>("hue-spacing").copied(),
+        min_contrast: ContrastConstraints {
+            foreground: matches.get_one::<f32>("min-fg-contrast").copied(),
+            hue: matches.get_one::<f32>("min-contrast").copied(),
+        },
+    }
+}
+
+fn matches_to_config(matches: &ArgMatches, palette_file: Option<&PaletteFile>) -> Result<Config> {
+    let mut config = match matches.get_one::<PathBuf>("config") {
+        Some(path) => Config::from_path(path)?,
+        None => Config::default(),
+    };
+    if let Some(palette_file) = palette_file {
+        config.name = palette_file.name.clone().or(config.name);
+        config.author = palette_file.author.clone().or(config.author);
+    }
+    match matches.get_one::<String>("order").map(String::as_str) {
+        Some("declaration") => config.order = VariableOrder::Declaration,
+        Some("alphabetical") => config.order = VariableOrder::Alphabetical,
+        _ => {},
+    }
+    match matches.get_one::<String>("color-space").map(String::as_str) {
+        Some("lab_contrast") => config.color_space = ColorSpace::LabContrast,
+        Some("oklab") => config.color_space = ColorSpace::Oklab,
+        Some("oklch") => config.color_space = ColorSpace::Oklch,
+        Some("linear_srgb") => config.color_space = ColorSpace::LinearSrgb,
+        _ => {},
+    }
+    if let Some(name) = matches.get_one::<String>("name") {
+        config.name = Some(name.clone());
+    }
+    if let Some(slug) = matches.get_one::<String>("slug") {
+        config.slug = Some(slug.clone());
+    }
+    if let Some(author) = matches.get_one::<String>("author") {
+        config.author = Some(author.clone());
+    }
+    Ok(config)
+}
+
+fn print_gamut_warnings(config: &Config) {
+    for color in out_of_gamut_colors(config) {
+        eprintln!("warning: {} is out of sRGB gamut and was mapped into it", color);
+    }
+}
 
-    if palette_arg != "-" {
-        config.palette = palette::Palette::from_str(palette_arg).map_err(|x| anyhow!("{}", x))?;
+/// Config with the palette of `PALETTE`: generated from the palette code, or
+/// from `--palette-file` or else config when it is `-`.
+fn matches_to_config_with_palette(matches: &ArgMatches) -> Result<Config> {
+    let palette_file = matches_to_palette_file(matches, "PALETTE")?;
+    let mut config = matches_to_config(matches, palette_file.as_ref())?;
+    match palette_file {
+        Some(palette_file) => config.palette = palette_file.to_palette(matches.get_one::<u64>("seed").copied()),
+        None if matches.get_one::<String>("PALETTE").map(String::as_str) != Some("-") => {
+            config.palette = generate(&matches_to_palette_option(matches, "PALETTE", None)?);
+        },
+        None => {},
     }
+    Ok(config)
+}
 
-    // Add config
+fn matches_to_formatted_variables(matches: &ArgMatches, config: &Config) -> Result<serde_json::Value> {
+    if matches.contains_id("warn-gamut") {
+        print_gamut_warnings(config);
+    }
 
-    let variables = get_variables(&config)?;
-    Ok(format_variables(&config, &variables))
+    Ok(to_data_with_config(config)?)
 }
 
 fn main() -> Result<()> {
     let matches = cli().get_matches();
     match matches.subcommand() {
         Some(("render", sub_matches)) => {
-            let formatted_variables = matches_to_formatted_variables(&sub_matches)?;
+            let config = matches_to_config_with_palette(sub_matches)?;
+            let formatted_variables = matches_to_formatted_variables(sub_matches, &config)?;
             let template_arg = sub_matches.get_one::<String>("TEMPLATE").unwrap();
 
             let template = if template_arg == "-" {
@@ -88,23 +268,124 @@ fn main() -> Result<()> {
             match sub_matches.get_one::<PathBuf>("DEST") {
                 None => template.render(&mut io::stdout(), &formatted_variables)?,
                 Some(dest) => {
-                    let mut dest_file = utils::get_write(&dest)?;
+                    let mut dest_file = utils::get_write(dest)?;
                     template.render(&mut dest_file, &formatted_variables)?
                 },
             };
+        }
+        Some(("generate", sub_matches)) => {
+            let palette_file = matches_to_palette_file(sub_matches, "INCOMPLETE_PALETTE")?;
+            let palette_option = matches_to_palette_option(sub_matches, "INCOMPLETE_PALETTE", palette_file.as_ref())?;
+            let count = *sub_matches.get_one::<usize>("count").unwrap();
+            let mut rng = match palette_option.seed {
+                Some(seed) => ChaCha8Rng::seed_from_u64(seed),
+                None => ChaCha8Rng::from_entropy(),
+            };
+            let format = sub_matches.get_one::<String>("format").unwrap();
+            let preview = compile_str(include_str!("../templates/preview.mustache"))?;
+            let mut config = matches_to_config(sub_matches, palette_file.as_ref())?;
+            let mut options = matches_to_generator_options(sub_matches);
+            if let Some(palette_file) = &palette_file {
+                options.is_dark = options.is_dark.or(palette_file.generator_options().is_dark);
+            }
 
-            return Ok(());
+            for _ in 0..count {
+                let violations;
+                (config.palette, violations) = generate_with_options(&palette_option, &options, &mut rng);
+                for violation in violations {
+                    eprintln!("warning: {}", violation);
+                }
+                if sub_matches.contains_id("warn-gamut") {
+                    print_gamut_warnings(&config);
+                }
+                match format.as_str() {
+                    "code" => println!("{}", config.palette),
+                    "preview" => {
+                        println!("{}", config.palette);
+                        preview.render(&mut io::stdout(), &to_data_with_config(&config)?)?;
+                    }
+                    "json" => println!("{}", serde_json::to_string(&to_data_with_config(&config)?)?),
+                    _ => unreachable!(),
+                }
+            }
         }
         Some(("preview", sub_matches)) => {
-            let formatted_variables = matches_to_formatted_variables(&sub_matches)?;
-            compile_str(include_str!("../templates/preview.mustache"))?.render(&mut io::stdout(), &formatted_variables)?;
+            let config = matches_to_config_with_palette(sub_matches)?;
+            let formatted_variables = matches_to_formatted_variables(sub_matches, &config)?;
+            let template = if sub_matches.contains_id("ansi256") {
+                include_str!("../templates/preview_256.mustache")
+            } else {
+                include_str!("../templates/preview.mustache")
+            };
+            compile_str(template)?.render(&mut io::stdout(), &formatted_variables)?;
+        }
+        Some(("counterpart", sub_matches)) => {
+            let config = matches_to_config_with_palette(sub_matches)?;
+            println!("{}", counterpart(&config.palette));
+        }
+        Some(("contrast", sub_matches)) => {
+            let config = matches_to_config_with_palette(sub_matches)?;
+            let formatted_variables = matches_to_formatted_variables(sub_matches, &config)?;
+            let bg = config.palette.colors[0];
+            let columns = ["foreground", "c1", "c2", "c3", "c4", "c5", "c6", "c7"];
+            print!("{:<6}", "");
+            for column in columns {
+                print!("{:>12}", column);
+            }
+            println!();
+            for shade in config.shades.keys() {
+                print!("{:<6}", shade);
+                for column in columns {
+                    // `contrast_bg` is rounded, so a ratio of 4.496 would pass as 4.50.
+                    let hex = formatted_variables[column][shade]["hex"].as_str()
+                        .ok_or_else(|| anyhow!("missing {}.{}", column, shade))?;
+                    let ratio = wcag_ratio(&Color::from_str(hex)?, &bg);
+                    let level = match WcagLevel::of(ratio) {
+                        WcagLevel::Fail => "fail",
+                        WcagLevel::AA => "AA",
+                        WcagLevel::AAA => "AAA",
+                    };
+                    print!("{:>7.2} {:<4}", ratio, level);
+                }
+                println!();
+            }
+        }
+        Some(("import", sub_matches)) => {
+            let path = sub_matches.get_one::<PathBuf>("FILE").unwrap();
+            let content = if path.as_os_str() == "-" {
+                read_stdin()?
+            } else {
+                std::fs::read_to_string(path)?
+            };
+            let palette = match sub_matches.get_one::<String>("FORMAT").unwrap().as_str() {
+                "base16" => import_base16(&content)?,
+                "alacritty" => import_alacritty(&content)?,
+                "kitty" => import_kitty(&content)?,
+                "xresources" => import_xresources(&content)?,
+                "iterm2" => import_iterm2(&content)?,
+                "windows-terminal" => import_windows_terminal(&content)?,
+                _ => unreachable!(),
+            };
+            println!("{}", palette);
+        }
+        Some(("validate-config", sub_matches)) => {
+            let config = matches_to_config(sub_matches, None)?;
+            let errors = validate_config(&config);
+            for error in &errors {
+                eprintln!("{}", error);
+            }
+            if !errors.is_empty() {
+                return Err(anyhow!("{} problem(s) found in config", errors.len()));
+            }
+            println!("config is valid");
         }
         Some(("list-variables", sub_matches)) => {
-            let formatted_variables = matches_to_formatted_variables(&sub_matches)?;
+            let config = matches_to_config_with_palette(sub_matches)?;
+            let formatted_variables = matches_to_formatted_variables(sub_matches, &config)?;
             println!("{}", serde_json::to_string(&formatted_variables)?);
         }
         _ => unreachable!()
     }
 
-    return Ok(());
+    Ok(())
 }
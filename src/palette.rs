@@ -1,9 +1,7 @@
 use std::{fmt, str::FromStr};
-use ext_palette::Srgb;
-use itertools::Itertools;
 use serde::{Serialize, de::{Visitor, self}, Deserialize, Deserializer};
 
-use crate::{Color, generator};
+use crate::{Color, css_color, generator, error::Error};
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub struct Palette {
@@ -11,13 +9,22 @@ pub struct Palette {
 }
 
 pub struct PaletteOption {
-    pub colors: [Option<Color>;9]
+    pub colors: [Option<Color>;9],
+    /// Seed used to generate the unspecified colors. Written as `@<seed>` at the end of a palette code.
+    pub seed: Option<u64>,
+}
+
+impl Default for PaletteOption {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl PaletteOption {
     pub fn new() -> PaletteOption {
         PaletteOption {
-            colors: [None; 9]
+            colors: [None; 9],
+            seed: None,
         }
     }
 }
@@ -32,14 +39,17 @@ impl fmt::Display for Palette {
 
 impl fmt::Display for PaletteOption {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let s = self.colors.map(|co| co.map_or("".to_string(), |c| format!("{:x}", c))).join("-");
+        let s = self.colors.map(|co| co.map_or("_".to_string(), |c| format!("{:x}", c))).join("-");
         f.write_str(&s)?;
+        if let Some(seed) = self.seed {
+            write!(f, "@{}", seed)?;
+        }
         Ok(())
     }
 }
 
 impl FromStr for Palette {
-    type Err = String;
+    type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let palette_option = PaletteOption::from_str(s)?;
@@ -48,38 +58,110 @@ impl FromStr for Palette {
 }
 
 impl FromStr for PaletteOption {
-    type Err = String;
+    type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let mut palette_option = PaletteOption::new();
-        let len = s.split('-').count();
+        let s = match s.split_once('@') {
+            None => s,
+            Some((s, seed)) => {
+                palette_option.seed = Some(seed.parse().map_err(|_| Error::InvalidSeed(seed.to_string()))?);
+                s
+            },
+        };
+        let colors = split_colors(s);
+        if colors.iter().any(|c| c.contains('=')) {
+            return palette_option.with_keyed_colors(&colors);
+        }
+        let wildcards = colors.iter().filter(|c| **c == "?").count();
+        if wildcards > 1 {
+            return Err(Error::InvalidPaletteCode("`?` can only be used once".to_string()));
+        }
+        let len = colors.len() - wildcards;
         if len > 9 {
-            return Err(format!("too many colors: {}", len));
+            return Err(Error::WrongColorCount(len));
         }
-        for (i, c) in s.split('-').enumerate() {
+        let mut i = 0;
+        for c in colors {
             match c {
                 "_" => (),
+                // Stands for as many unspecified colors as needed.
                 "?" => {
-                    return Ok(palette_option);
+                    i += 9 - len;
+                    continue;
                 },
-                c if const_regex::match_regex!(r"[0-9a-fA-F]{6}", c.as_bytes()) => {
-                    let r = u8::from_str_radix(&c[0..2], 16).unwrap();
-                    let g = u8::from_str_radix(&c[2..4], 16).unwrap();
-                    let b = u8::from_str_radix(&c[4..6], 16).unwrap();
-                    palette_option.colors[i] = Some(Srgb::new(r,g,b));
+                c => {
+                    let color = css_color::parse(c).ok_or_else(|| Error::InvalidColor { position: i + 1, value: c.to_string() })?;
+                    palette_option.colors[i] = Some(color);
                 },
-                _ => {
-                    return Err(format!("{}th color in palette is wrong format: {}", i, c));
-                }
             }
+            i += 1;
         }
-        if len != 9 {
-            return Err(format!("wrong number of colors: {}", len));
+        if i != 9 {
+            return Err(Error::WrongColorCount(len));
         }
         Ok(palette_option)
     }
 }
 
+/// Slot names of keyed palette codes, e.g. `bg=1d2021,c3=fabd2f`.
+const SLOTS: [&str; 9] = ["bg", "fg", "c1", "c2", "c3", "c4", "c5", "c6", "c7"];
+
+impl PaletteOption {
+    /// Sets the colors of `key=color` pairs, also accepting `background` and `foreground` as keys.
+    fn with_keyed_colors(mut self, colors: &[&str]) -> Result<PaletteOption, Error> {
+        let mut given = [false; 9];
+        for c in colors {
+            let (key, value) = c.split_once('=')
+                .ok_or_else(|| Error::InvalidPaletteCode(format!("`{}` has no slot, either all or no colors have one", c)))?;
+            let i = match key {
+                "background" => 0,
+                "foreground" => 1,
+                key => SLOTS.iter().position(|slot| *slot == key)
+                    .ok_or_else(|| Error::InvalidPaletteCode(format!("unknown slot `{}`, expecting bg, fg or c1 to c7", key)))?,
+            };
+            if given[i] {
+                return Err(Error::InvalidPaletteCode(format!("slot `{}` is given twice", SLOTS[i])));
+            }
+            given[i] = true;
+            if value != "_" {
+                let color = css_color::parse(value).ok_or_else(|| Error::InvalidColor { position: i + 1, value: value.to_string() })?;
+                self.colors[i] = Some(color);
+            }
+        }
+        Ok(self)
+    }
+}
+
+/// Splits a palette code at `-`, `,` and whitespace, except inside of
+/// parentheses like in `rgb(1, 2, 3)`.
+fn split_colors(s: &str) -> Vec<&str> {
+    let mut colors = Vec::new();
+    let mut depth = 0;
+    let mut start = None;
+    for (i, c) in s.char_indices() {
+        let is_separator = depth == 0 && (c == '-' || c == ',' || c.is_whitespace());
+        match c {
+            '(' => depth += 1,
+            // A stray `)` only makes its own color invalid.
+            ')' => depth = (depth - 1).max(0),
+            _ => (),
+        }
+        match (is_separator, start) {
+            (true, Some(j)) => {
+                colors.push(&s[j..i]);
+                start = None;
+            },
+            (false, None) => start = Some(i),
+            _ => (),
+        }
+    }
+    if let Some(j) = start {
+        colors.push(&s[j..]);
+    }
+    colors
+}
+
 impl Serialize for Palette {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -150,6 +232,9 @@ impl<'de> Deserialize<'de> for PaletteOption {
     }
 }
 
+#[cfg(test)]
+use itertools::Itertools;
+
 #[test]
 fn from_str_works() {
     let palette_str = "000000-ffffff-222222-333333-444444-555555-666666-777777-888888";
@@ -199,4 +284,76 @@ fn from_str_works_with_question_mark() {
             _ => assert_eq!(*e, a),
         }
     });
-}
\ No newline at end of file
+}
+#[test]
+fn from_str_works_with_seed() {
+    let palette_option = PaletteOption::from_str("_-ffffff-?@42").unwrap();
+    assert_eq!(palette_option.seed, Some(42));
+    assert_eq!(palette_option.to_string(), "_-ffffff-_-_-_-_-_-_-_@42");
+    assert_eq!(Palette::from_str("_-ffffff-?@42").unwrap(), Palette::from_str("_-ffffff-?@42").unwrap());
+    assert!(PaletteOption::from_str("?@abc").is_err());
+}
+
+#[test]
+fn to_string_writes_underscore_for_unspecified_colors() {
+    let palette_option = PaletteOption::from_str("bg=000000,c7=ff8000").unwrap();
+    assert_eq!(palette_option.to_string(), "000000-_-_-_-_-_-_-_-ff8000");
+    assert_eq!(PaletteOption::new().to_string(), "_-_-_-_-_-_-_-_-_");
+    assert_eq!(PaletteOption::from_str(&palette_option.to_string()).unwrap().colors, palette_option.colors);
+}
+
+#[test]
+fn from_str_errors() {
+    assert!(matches!(
+        PaletteOption::from_str("000000-ffffff-xyz"),
+        Err(Error::InvalidColor { position: 3, value }) if value == "xyz"));
+    assert!(matches!(
+        PaletteOption::from_str(")-?"),
+        Err(Error::InvalidColor { position: 1, value }) if value == ")"));
+    assert!(matches!(PaletteOption::from_str("000000-ffffff"), Err(Error::WrongColorCount(2))));
+    assert!(matches!(PaletteOption::from_str(&["000000"; 10].join("-")), Err(Error::WrongColorCount(10))));
+}
+
+#[test]
+fn from_str_is_lenient() {
+    let canonical = "282936-e9e9f4-ff5555-ffb86c-f1fa8c-50fa7b-8be9fd-bd93f9-ff79c6";
+    let codes = [
+        "#282936,#E9E9F4,#FF5555,#FFB86C,#F1FA8C,#50FA7B,#8BE9FD,#BD93F9,#FF79C6",
+        "#282936, #E9E9F4, #FF5555, #FFB86C, #F1FA8C, #50FA7B, #8BE9FD, #BD93F9, #FF79C6",
+        "282936\ne9e9f4\nff5555\nffb86c\nf1fa8c\n50fa7b\n8be9fd\nbd93f9\nff79c6\n",
+        "282936 e9e9f4 rgb(255, 85, 85) rgb(255 184 108) f1fa8c 50fa7b 8be9fd bd93f9 ff79c6",
+    ];
+    for code in codes {
+        assert_eq!(Palette::from_str(code).unwrap().to_string(), canonical, "{}", code);
+    }
+    let palette = Palette::from_str("000-fff-red-orange-yellow-lime-cyan-blue-rebeccapurple").unwrap();
+    assert_eq!(palette.to_string(), "000000-ffffff-ff0000-ffa500-ffff00-00ff00-00ffff-0000ff-663399");
+    let palette_option = PaletteOption::from_str("#000, hsl(0 0% 100%), _, ?").unwrap();
+    assert_eq!(palette_option.to_string(), "000000-ffffff-_-_-_-_-_-_-_");
+}
+
+#[test]
+fn from_str_works_with_question_mark_anywhere() {
+    let palette_option = PaletteOption::from_str("000000-?-ff8000").unwrap();
+    assert_eq!(palette_option.to_string(), "000000-_-_-_-_-_-_-_-ff8000");
+    let palette_option = PaletteOption::from_str("?-000000-ffffff").unwrap();
+    assert_eq!(palette_option.to_string(), "_-_-_-_-_-_-_-000000-ffffff");
+    let full = ["000000"; 9].join("-");
+    assert_eq!(PaletteOption::from_str(&format!("{}-?", full)).unwrap().to_string(), full);
+    assert!(matches!(PaletteOption::from_str("?-000000-?"), Err(Error::InvalidPaletteCode(_))));
+    assert!(matches!(PaletteOption::from_str(&format!("{}-000000-?", full)), Err(Error::WrongColorCount(10))));
+}
+
+#[test]
+fn from_str_works_with_slots() {
+    let palette_option = PaletteOption::from_str("bg=1d2021,fg=d5c4a1,c3=fabd2f@7").unwrap();
+    assert_eq!(palette_option.to_string(), "1d2021-d5c4a1-_-_-fabd2f-_-_-_-_@7");
+    let palette_option = PaletteOption::from_str("c7=red foreground=#fff").unwrap();
+    assert_eq!(palette_option.to_string(), "_-ffffff-_-_-_-_-_-_-ff0000");
+    assert!(matches!(PaletteOption::from_str("bg=000,c8=fff"), Err(Error::InvalidPaletteCode(_))));
+    assert!(matches!(PaletteOption::from_str("bg=000,background=fff"), Err(Error::InvalidPaletteCode(_))));
+    assert!(matches!(PaletteOption::from_str("bg=000,fff"), Err(Error::InvalidPaletteCode(_))));
+    assert!(matches!(
+        PaletteOption::from_str("bg=000,c2=xyz"),
+        Err(Error::InvalidColor { position: 4, value }) if value == "xyz"));
+}
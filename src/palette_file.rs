@@ -0,0 +1,172 @@
+use std::{path::Path, str::FromStr};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Serialize, Deserialize};
+
+use crate::{Color, palette::{Palette, PaletteOption}, generator::{self, GeneratorOptions}, error::{Error, Result}};
+
+/// Whether a palette has a dark background and light foreground or the opposite.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Variant {
+    Dark,
+    Light,
+}
+
+/// A palette written as a YAML, JSON or TOML document instead of a palette code:
+///
+/// ```yaml
+/// name: Dracula
+/// variant: dark
+/// background: "#282936"
+/// foreground: "#e9e9f4"
+/// c1: "#ff5555"
+/// # ...
+/// ```
+///
+/// Colors accept the same notations as palette codes. Missing colors are
+/// generated, `variant` chooses which kind of background and foreground.
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PaletteFile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variant: Option<Variant>,
+    #[serde(default, with = "color", skip_serializing_if = "Option::is_none")]
+    pub background: Option<Color>,
+    #[serde(default, with = "color", skip_serializing_if = "Option::is_none")]
+    pub foreground: Option<Color>,
+    #[serde(default, with = "color", skip_serializing_if = "Option::is_none")]
+    pub c1: Option<Color>,
+    #[serde(default, with = "color", skip_serializing_if = "Option::is_none")]
+    pub c2: Option<Color>,
+    #[serde(default, with = "color", skip_serializing_if = "Option::is_none")]
+    pub c3: Option<Color>,
+    #[serde(default, with = "color", skip_serializing_if = "Option::is_none")]
+    pub c4: Option<Color>,
+    #[serde(default, with = "color", skip_serializing_if = "Option::is_none")]
+    pub c5: Option<Color>,
+    #[serde(default, with = "color", skip_serializing_if = "Option::is_none")]
+    pub c6: Option<Color>,
+    #[serde(default, with = "color", skip_serializing_if = "Option::is_none")]
+    pub c7: Option<Color>,
+}
+
+mod color {
+    use serde::{Serialize, Deserialize, Deserializer, Serializer, de};
+
+    use crate::{Color, css_color};
+
+    pub fn serialize<S: Serializer>(color: &Option<Color>, serializer: S) -> Result<S::Ok, S::Error> {
+        color.map(|c| format!("#{:x}", c)).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Color>, D::Error> {
+        match Option::<String>::deserialize(deserializer)? {
+            None => Ok(None),
+            Some(s) => css_color::parse(&s).map(Some).ok_or_else(|| de::Error::custom(format!("invalid color: {}", s))),
+        }
+    }
+}
+
+impl PaletteFile {
+    /// Reads a palette file, as TOML when its extension is `.toml` and as YAML or JSON otherwise.
+    pub fn from_path(path: &Path) -> Result<PaletteFile> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| Error::PaletteFile(format!("failed to read {}: {}", path.display(), e)))?;
+        if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("toml")) {
+            Self::from_toml(&content)
+        } else {
+            Self::from_str(&content)
+        }
+    }
+
+    pub fn from_toml(s: &str) -> Result<PaletteFile> {
+        toml::from_str(s).map_err(|e| Error::PaletteFile(e.to_string()))
+    }
+
+    pub fn to_palette_option(&self) -> PaletteOption {
+        PaletteOption {
+            colors: [self.background, self.foreground, self.c1, self.c2, self.c3, self.c4, self.c5, self.c6, self.c7],
+            seed: None,
+        }
+    }
+
+    /// Generator options generating a background and foreground of `variant`.
+    pub fn generator_options(&self) -> GeneratorOptions {
+        GeneratorOptions {
+            is_dark: self.variant.map(|variant| variant == Variant::Dark),
+            ..GeneratorOptions::default()
+        }
+    }
+
+    /// Generates the missing colors, always the same ones for the same `seed`.
+    pub fn to_palette(&self, seed: Option<u64>) -> Palette {
+        let palette_option = self.to_palette_option();
+        let options = self.generator_options();
+        match seed {
+            Some(seed) => generator::generate_with_options(&palette_option, &options, &mut ChaCha8Rng::seed_from_u64(seed)).0,
+            None => generator::generate_with_options(&palette_option, &options, &mut rand::thread_rng()).0,
+        }
+    }
+}
+
+impl FromStr for PaletteFile {
+    type Err = Error;
+
+    /// Parses a YAML or JSON palette file.
+    fn from_str(s: &str) -> Result<PaletteFile> {
+        serde_yaml::from_str(s).map_err(|e| Error::PaletteFile(e.to_string()))
+    }
+}
+
+impl From<&Palette> for PaletteFile {
+    fn from(palette: &Palette) -> PaletteFile {
+        let [background, foreground, c1, c2, c3, c4, c5, c6, c7] = palette.colors.map(Some);
+        PaletteFile { background, foreground, c1, c2, c3, c4, c5, c6, c7, ..PaletteFile::default() }
+    }
+}
+
+#[test]
+fn from_str_works() {
+    let yaml = r##"
+# Dracula, with a muted red
+name: Dracula
+author: Zeno Rocha
+variant: dark
+background: "#282936"
+foreground: E9E9F4
+c1: rgb(230, 90, 90)
+c2: "#FFB86C"
+c3: "#F1FA8C"
+c4: "#50FA7B"
+c5: "#8BE9FD"
+c6: "#BD93F9"
+c7: "#FF79C6"
+"##;
+    let palette_file = PaletteFile::from_str(yaml).unwrap();
+    assert_eq!(palette_file.name.as_deref(), Some("Dracula"));
+    assert_eq!(palette_file.variant, Some(Variant::Dark));
+    assert_eq!(palette_file.to_palette_option().to_string(), "282936-e9e9f4-e65a5a-ffb86c-f1fa8c-50fa7b-8be9fd-bd93f9-ff79c6");
+
+    let json = serde_json::to_string(&palette_file).unwrap();
+    assert_eq!(PaletteFile::from_str(&json).unwrap(), palette_file);
+    let toml = toml::to_string(&palette_file).unwrap();
+    assert_eq!(PaletteFile::from_toml(&toml).unwrap(), palette_file);
+}
+
+#[test]
+fn from_str_errors() {
+    assert!(PaletteFile::from_str("background: nope").is_err());
+    assert!(PaletteFile::from_str("c8: '#000000'").is_err());
+    assert!(PaletteFile::from_toml("variant = \"dim\"").is_err());
+    let partial = PaletteFile::from_toml("variant = \"light\"\nc1 = \"red\"").unwrap();
+    assert_eq!(partial.to_palette_option().to_string(), "_-_-ff0000-_-_-_-_-_-_");
+    assert_eq!(partial.generator_options().is_dark, Some(false));
+    let palette = partial.to_palette(Some(42));
+    assert_eq!(palette, partial.to_palette(Some(42)));
+    assert_eq!(palette.colors[2], Color::new(255, 0, 0));
+}
@@ -1,14 +1,16 @@
 use std::str::FromStr;
+use rand::SeedableRng;
+use serde::Serialize;
 
 use crate::{config, base9};
-use crate::Palette;
-use mustache::compile_str;
+use crate::{Palette, PaletteOption, GeneratorOptions, ContrastViolation};
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen(typescript_custom_section)]
 const TS_APPEND_CONTENT: &'static str = r#""#;
 
 //TODO
+#[allow(dead_code)]
 pub fn set_panic_hook() {
     // When the `console_error_panic_hook` feature is enabled, we can call the
     // `set_panic_hook` function at least once during initialization, and then
@@ -39,31 +41,59 @@ pub fn set_panic_hook() {
 
 #[wasm_bindgen(js_name=getData)]
 pub fn get_data(palette: &str) -> Result<JsValue, JsError> {
-    let palette = Palette::from_str(palette).map_err(|x| JsError::new(&x))?;
+    let palette = Palette::from_str(palette).map_err(|x| JsError::new(&x.to_string()))?;
     let config = config::Config::from_palette(palette);
 
     let variables = base9::get_variables(&config).map_err(|x| JsError::new(&x.to_string()))?;
     let formatted_variables = base9::format_variables(&config, &variables);
+    #[allow(deprecated)]
     JsValue::from_serde(&formatted_variables).map_err(|x| JsError::new(&x.to_string()))
 }
 
 #[wasm_bindgen(js_name=getColors)]
 pub fn get_colors(palette: &str) -> Result<JsValue, JsError> {
-    let palette = Palette::from_str(palette).map_err(|x| JsError::new(&x))?;
+    let palette = Palette::from_str(palette).map_err(|x| JsError::new(&x.to_string()))?;
     let config = config::Config::from_palette(palette);
 
     let variables = base9::get_variables(&config).map_err(|x| JsError::new(&x.to_string()))?;
-    let formatted_variables = base9::map_color_map(&variables, |c| format!("#{:x}", c).into());
+    let formatted_variables = base9::map_color_map(&variables, &|c| format!("#{:x}", c.color).into());
+    #[allow(deprecated)]
     JsValue::from_serde(&formatted_variables).map_err(|x| JsError::new(&x.to_string()))
 }
 
 #[wasm_bindgen(js_name=renderString)]
 pub fn render_str(palette: &str, template_str: &str) -> Result<JsValue, JsError> {
-    let palette = Palette::from_str(palette).map_err(|x| JsError::new(&x))?;
+    let palette = Palette::from_str(palette).map_err(|x| JsError::new(&x.to_string()))?;
     let config = config::Config::from_palette(palette);
 
-    let variables = base9::get_variables(&config).map_err(|x| JsError::new(&x.to_string()))?;
-    let formatted_variables = base9::format_variables(&config, &variables);
-    let template = compile_str(&template_str).unwrap();
-    Ok(template.render_to_string(&formatted_variables).unwrap().into())
+    let rendered = crate::render_string(&config, template_str).map_err(|x| JsError::new(&x.to_string()))?;
+    Ok(rendered.into())
+}
+
+#[derive(Serialize)]
+struct Generated {
+    palette: String,
+    violations: Vec<ContrastViolation>,
+}
+
+/// Fills the unspecified colors of palette code `palette` and returns
+/// `{palette, violations}`, the palette code and the colors below their
+/// `min_contrast`. `options` is an optional `GeneratorOptions` object, e.g.
+/// `{is_dark: false, hue_c: {start: 10, end: 20}}`.
+#[wasm_bindgen(js_name=generate)]
+pub fn generate(palette: &str, options: JsValue) -> Result<JsValue, JsError> {
+    let palette_option = PaletteOption::from_str(palette).map_err(|x| JsError::new(&x.to_string()))?;
+    let options: GeneratorOptions = if options.is_undefined() || options.is_null() {
+        GeneratorOptions::default()
+    } else {
+        #[allow(deprecated)]
+        options.into_serde().map_err(|x| JsError::new(&x.to_string()))?
+    };
+    let (palette, violations) = match palette_option.seed {
+        Some(seed) => crate::generate_with_options(&palette_option, &options, &mut rand_chacha::ChaCha8Rng::seed_from_u64(seed)),
+        None => crate::generate_with_options(&palette_option, &options, &mut rand::thread_rng()),
+    };
+    let generated = Generated { palette: palette.to_string(), violations };
+    #[allow(deprecated)]
+    JsValue::from_serde(&generated).map_err(|x| JsError::new(&x.to_string()))
 }
@@ -1,8 +1,9 @@
+#![allow(clippy::len_zero, clippy::needless_return, clippy::assertions_on_constants, clippy::chars_next_cmp)]
+
 use std::{fs, str::FromStr, path::Path};
 use ext_palette::Srgb;
-use itertools::Itertools;
 
-use base9_builder::{self, to_mustache_data, Palette, to_data};
+use base9_builder::{self, Config, Palette, out_of_gamut_colors, to_data, to_data_with_config};
 use jsonschema::{JSONSchema, output::BasicOutput};
 
 pub type Rgb = Srgb<u8>;
@@ -24,6 +25,38 @@ fn correct_absolute_color() {
     }
 }
 
+/// Returns the palettes in `tests/palette_with_correct_absolute_order` that `config_str` maps correctly.
+fn correctly_mapped_palettes(config_str: &str) -> Vec<String> {
+    let contents = fs::read_to_string("tests/palette_with_correct_absolute_order").unwrap();
+    let names = ["red", "yellow", "green", "cyan", "blue", "magenta"];
+    contents.lines().filter(|palette_str| {
+        if palette_str.is_empty() || palette_str.starts_with('#') {
+            return false;
+        }
+        let mut config = Config::from_str(config_str).unwrap();
+        config.palette = Palette::from_str(palette_str).unwrap();
+        let data = to_data_with_config(&config).unwrap();
+        let actual = names.map(|name| data[name]["p100"]["hex"].as_str().unwrap().to_string()).join("-");
+        actual == palette_str[((6+1)*2)..((6+1)*(6+2)-1)]
+    }).map(String::from).collect()
+}
+
+#[test]
+fn absolute_color_metrics() {
+    let all = vec![
+        "1d2021-d5c4a1-ff0000-ffff00-00ff00-00ffff-0000ff-ff00ff-ff8000",
+        "282936-e9e9f4-ff5555-f1fa8c-50fa7b-8be9fd-bd93f9-ff79c6-ffb86c",
+        "1d2021-d5c4a1-fb4934-fabd2f-b8bb26-8ec07c-83a598-d3869b-fe8019",
+        "001153-7de0ff-ea4f9c-e3c0ae-97bda5-00c4ea-00abff-968dff-ee8394",
+        "232323-f7f7f7-ff9770-e9ff70-9df87e-70d6ff-c79cff-ffa0c5-ffd670",
+    ];
+    let without_gruvbox_and_blue = vec![all[0], all[1], all[4]];
+    assert_eq!(correctly_mapped_palettes("absolute: {metric: rgb_offset}"), all);
+    assert_eq!(correctly_mapped_palettes("absolute: {metric: rgb_offset, weight: null}"), all);
+    assert_eq!(correctly_mapped_palettes("absolute: {metric: lch}"), without_gruvbox_and_blue);
+    assert_eq!(correctly_mapped_palettes("absolute: {metric: ciede2000}"), without_gruvbox_and_blue);
+}
+
 #[test]
 fn match_schema() {
     // Not working, not sure if we should use json schema.
@@ -52,4 +85,291 @@ fn match_schema() {
     }
 }
     
-}
\ No newline at end of file
+}
+#[test]
+fn generate_subcommand() {
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_base9-builder"))
+        .args(["generate", "_-ffffff-?", "--count", "3"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let palettes: Vec<Palette> = stdout.lines().map(|line| Palette::from_str(line).unwrap()).collect();
+    assert_eq!(palettes.len(), 3);
+    for palette in palettes {
+        assert_eq!(palette.colors[1], Rgb::new(0xff, 0xff, 0xff));
+    }
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_base9-builder"))
+        .args(["generate", "?", "--format", "json"])
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(json["background"]["hex"].is_string());
+
+    let seeded = || std::process::Command::new(env!("CARGO_BIN_EXE_base9-builder"))
+        .args(["generate", "?", "--count", "2", "--seed", "42"])
+        .output()
+        .unwrap()
+        .stdout;
+    assert_eq!(seeded(), seeded());
+}
+#[test]
+fn bad_config_returns_error() {
+    use base9_builder::{Error, render_string};
+    let config = Config::from_str("colors: {diff: {added: green.p100, removed: nope.p100}}").unwrap();
+    match to_data_with_config(&config) {
+        Err(Error::UnknownReference { reference, alias }) => {
+            assert_eq!(reference, "nope.p100");
+            assert_eq!(alias, "diff.removed");
+        },
+        x => panic!("expecting UnknownReference, got {:?}", x),
+    }
+
+    let config = Config::from_str("colors: {bad: background.p100}").unwrap();
+    assert!(matches!(to_data_with_config(&config), Err(Error::ReferenceIntoColor { .. })));
+
+    assert!(matches!(Config::from_str("colors: [1, 2]"), Err(Error::Config(_))));
+    assert!(matches!(render_string(&Config::default(), "{{#c1}}"), Err(Error::Template(_))));
+}
+
+#[test]
+fn variables_are_ordered() {
+    let config = Config::default();
+    let data = to_data_with_config(&config).unwrap();
+    assert_eq!(data.to_string(), to_data_with_config(&config).unwrap().to_string());
+    let keys: Vec<&String> = data.as_object().unwrap().keys().collect();
+    assert_eq!(keys[0..4], ["background", "foreground", "c1", "c2"]);
+    let shades: Vec<&String> = data["c1"].as_object().unwrap().keys().collect();
+    assert_eq!(shades, ["p10", "p25", "p50", "p75", "p100", "p125"]);
+
+    let config = Config::from_str("order: alphabetical").unwrap();
+    let data = to_data_with_config(&config).unwrap();
+    let keys: Vec<&String> = data["ansi"].as_object().unwrap().keys().collect();
+    let mut sorted = keys.clone();
+    sorted.sort();
+    assert_eq!(keys, sorted);
+}
+
+#[test]
+fn references_resolve_in_dependency_order() {
+    use base9_builder::{Error, validate_config};
+    let config = Config::from_str("colors: {early: late.accent, late: {accent: c3}}").unwrap();
+    assert!(validate_config(&config).is_empty());
+    let data = to_data_with_config(&config).unwrap();
+    assert_eq!(data["early"], data["c3"]);
+
+    let config = Config::from_str("colors: {a: b, b: {x: a.x}, ok: c1, bad: {one: nope, two: c1.nope}}").unwrap();
+    let errors = validate_config(&config);
+    assert_eq!(errors.len(), 3, "{:?}", errors);
+    match &errors[0] {
+        Error::CyclicReference(cycle) => assert_eq!(cycle, &["a", "b", "b.x", "a"]),
+        x => panic!("expecting CyclicReference, got {:?}", x),
+    }
+    let aliases: Vec<&str> = errors[1..].iter().map(|e| match e {
+        Error::UnknownReference { alias, .. } => alias.as_str(),
+        x => panic!("expecting UnknownReference, got {:?}", x),
+    }).collect();
+    assert_eq!(aliases, ["bad.one", "bad.two"]);
+    assert!(matches!(to_data_with_config(&config), Err(Error::CyclicReference(_))));
+}
+
+#[test]
+fn literal_and_expression_colors() {
+    let config = Config::from_str(r##"
+palette: "000000-ffffff-ff0000-ffff00-00ff00-00ffff-0000ff-ff00ff-808080"
+colors:
+  orange: "#FF8800"
+  half: mix(background, foreground.p100, 0.5)
+  lighter: lighten(background, 50%)
+  darker: darken(foreground.p100, 0.5)
+  faded: alpha(c1, 0.5)
+  text_on_red: contrast_on(c1.p100)
+  text_on_black: contrast_on(#000000)
+  nested: darken(mix(#ffffff, orange, 0.5), 0)
+  negative: lighten(orange, -10%)
+  darkened: darken(orange, 10%)
+"##).unwrap();
+    let data = to_data_with_config(&config).unwrap();
+    let format = |path: &str, format: &str| {
+        let mut value = &data;
+        for key in path.split('.') {
+            value = &value[key];
+        }
+        value[format].as_str().unwrap().to_string()
+    };
+    let hex = |path: &str| format(path, "hex");
+    assert_eq!(hex("orange"), "ff8800");
+    assert_eq!(hex("lighter"), hex("darker"));
+    assert_eq!(format("faded.p100", "hex8"), "ff000080");
+    assert_eq!(format("orange", "hex8"), "ff8800ff");
+    assert_eq!(hex("text_on_red"), "000000");
+    assert_eq!(hex("text_on_black"), "ffffff");
+    assert_ne!(hex("half"), hex("background"));
+    assert_eq!(hex("nested"), "ffc18a");
+    assert_eq!(hex("negative"), hex("darkened"));
+    assert_ne!(hex("negative"), hex("orange"));
+
+    let config = Config::from_str(r#"colors: {bad: "mix(c1, nope, 0.5)"}"#).unwrap();
+    assert_eq!(base9_builder::validate_config(&config).len(), 1);
+    assert!(Config::from_str(r#"colors: {bad: "mix(c1, 0.5)"}"#).is_err());
+}
+
+#[test]
+fn formats_of_known_color() {
+    let config = Config::from_str(r##"colors: {orange: "#ff8000"}"##).unwrap();
+    let data = to_data_with_config(&config).unwrap();
+    let format = |format: &str| data["orange"][format].as_str().unwrap().to_string();
+    assert_eq!(format("int_rgb"), "16744448");
+    assert_eq!(format("int_bgr"), "33023");
+    assert_eq!(format("hsl_css"), "hsl(30.1, 100%, 50%)");
+    assert_eq!(format("hsv_css"), "hsv(30.1, 100%, 100%)");
+    assert_eq!(format("oklch_css"), "oklch(0.732 0.186 53)");
+    assert_eq!(format("rgb_slash"), "ff/80/00");
+    assert_eq!(format("rgb_css"), "rgb(255, 128, 0)");
+    assert_eq!(format("hex_0x"), "0xff8000");
+}
+
+#[test]
+fn alpha_of_aliases() {
+    let config = Config::from_str(r##"
+colors:
+  selection: {ref: c1.p50, alpha: 0.3}
+  overlay: {ref: "#000000", alpha: 0.5}
+  shaded: {ref: c2, alpha: 1}
+  group: {ref: c1, other: c2}
+"##).unwrap();
+    let data = to_data_with_config(&config).unwrap();
+    assert_eq!(data["selection"]["hex"], data["c1"]["p50"]["hex"]);
+    assert_eq!(data["selection"]["hex_a"], "4d");
+    assert_eq!(data["selection"]["int_a"], "77");
+    assert_eq!(data["overlay"]["hex8"], "00000080");
+    assert_eq!(data["overlay"]["dec_a"], (128. / 255.).to_string());
+    assert_eq!(data["shaded"]["p10"]["hex_a"], "ff");
+    assert_eq!(data["group"]["ref"], data["c1"]);
+    assert_eq!(data["background"]["hex_a"], "ff");
+}
+
+#[test]
+fn polarity_and_metadata() {
+    let config = Config::from_str(r#"
+palette: "282936-E9E9F4-FF5555-FFB86C-F1FA8C-50FA7B-8BE9FD-BD93F9-FF79C6"
+name: Dracula Pro
+author: me
+"#).unwrap();
+    let data = to_data_with_config(&config).unwrap();
+    assert_eq!(data["IS_DARK"], true);
+    assert_eq!(data["IS_LIGHT"], false);
+    assert_eq!(data["VARIANT"], "dark");
+    assert_eq!(data["NAME"], "Dracula Pro");
+    assert_eq!(data["SLUG"], "dracula-pro");
+    assert_eq!(data["AUTHOR"], "me");
+
+    let config = Config::from_str(r#"palette: "E9E9F4-282936-FF5555-FFB86C-F1FA8C-50FA7B-8BE9FD-BD93F9-FF79C6""#).unwrap();
+    let data = to_data_with_config(&config).unwrap();
+    assert_eq!(data["VARIANT"], "light");
+    assert!(data.get("NAME").is_none());
+}
+
+#[test]
+fn color_space_changes_shades() {
+    let palette = "282936-E9E9F4-FF5555-FFB86C-F1FA8C-50FA7B-8BE9FD-BD93F9-3050FF";
+    let shades = |color_space: &str| {
+        let config = Config::from_str(&format!("{{palette: \"{}\", color_space: {}}}", palette, color_space)).unwrap();
+        let data = to_data_with_config(&config).unwrap();
+        (data["c7"]["p50"]["hex"].clone(), data["c7"]["p100"]["hex"].clone())
+    };
+    let (lab_p50, lab_p100) = shades("lab_contrast");
+    for color_space in ["oklab", "oklch", "linear_srgb"] {
+        let (p50, p100) = shades(color_space);
+        assert_ne!(p50, lab_p50, "{}", color_space);
+        assert_eq!(p100, lab_p100, "{}", color_space);
+    }
+    assert!(Config::from_str("color_space: hsl").is_err());
+}
+
+#[test]
+fn out_of_gamut_colors_are_listed() {
+    let config = Config::from_str(r#"
+palette: "282936-E9E9F4-FF5555-FFB86C-F1FA8C-50FA7B-8BE9FD-BD93F9-3050FF"
+colors:
+  glow: lighten(c7.p100, 30%)
+  dim: darken(foreground.p50, 10%)
+"#).unwrap();
+    let colors = out_of_gamut_colors(&config);
+    assert!(colors.contains(&"c7.p125".to_string()), "{:?}", colors);
+    assert!(colors.contains(&"glow".to_string()), "{:?}", colors);
+    assert!(!colors.iter().any(|c| c.ends_with(".p100") || c.ends_with(".p50") || c == "dim"), "{:?}", colors);
+}
+
+#[test]
+fn contrast_subcommand_uses_unrounded_ratio() {
+    let contrast = |palette: &str| {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_base9-builder"))
+            .args(["contrast", palette])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+    // c1.p75 is just below 4.5 and foreground.p75 just below 7.
+    let output = contrast("f0efcd-451417-005075-03553f-64377c-455000-990051-9b0014-763b00");
+    assert!(output.contains("4.50 fail"), "{}", output);
+    let output = contrast("bcf8b5-311142-6d277b-49472a-004d6b-064394-85184c-005052-723328");
+    assert!(output.contains("7.00 AA "), "{}", output);
+}
+
+#[test]
+fn render_palette_file() {
+    let dir = std::env::temp_dir().join(format!("base9-builder-render-palette-file-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let palette_file = dir.join("x.yaml");
+    fs::write(&palette_file, "name: Half Dracula\nvariant: light\nc1: \"#ff5555\"\n").unwrap();
+    let template = dir.join("template.mustache");
+    fs::write(&template, "{{PALETTE}} {{NAME}} {{VARIANT}}").unwrap();
+    let render = |palette: &str| {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_base9-builder"))
+            .args(["render", palette, template.to_str().unwrap(), "--seed", "42", "--palette-file", palette_file.to_str().unwrap()])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let output = render("-");
+    assert_eq!(output, render("-"));
+    let (palette, rest) = output.split_once(' ').unwrap();
+    assert_eq!(rest, "Half Dracula light");
+    assert_eq!(&palette[14..20], "ff5555");
+
+    // The palette file only applies to `-`.
+    let output = render("000000-ffffff-?");
+    assert!(output.starts_with("000000-ffffff-"), "{}", output);
+    assert!(output.ends_with(" dark"), "{}", output);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn import_subcommand_reads_stdin() {
+    use std::{io::Write, process::{Command, Stdio}};
+    let mut child = Command::new(env!("CARGO_BIN_EXE_base9-builder"))
+        .args(["import", "kitty", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"# Tomorrow Night
+background #1d1f21
+foreground #c5c8c6
+color1 #cc6666
+color2 #b5bd68
+color3 #f0c674
+color4 #81a2be
+color5 #b294bb
+color6 #8abeb7
+color9 #d54e53
+").unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "1d1f21-c5c8c6-cc6666-d54e53-f0c674-b5bd68-8abeb7-81a2be-b294bb\n");
+}